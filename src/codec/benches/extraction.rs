@@ -0,0 +1,127 @@
+//! Throughput of extracting the stdout stream of a file-backed capture, by the
+//! decoder through userspace buffers, like `docker-stream-decoder
+//! --no-zero-copy` does, and by the zero-copy extractor.
+//!
+//! Run with `cargo bench -p docker_stream_codec`.
+
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use docker_stream_codec::{DockerStreamDecoder, MuxWriter, StreamType};
+
+#[path = "../src/temp_path.rs"]
+mod temp_path;
+use temp_path::temp_path;
+
+const CAPTURE_SIZE: usize = 64 * 1024 * 1024;
+const BUFFER_SIZE: usize = 8192;
+
+/// Writes a capture of stdout and stderr frames of `frame_size` bytes, taking
+/// turns.
+fn write_capture(path: &Path, frame_size: usize) -> io::Result<()> {
+    let mux = MuxWriter::new(BufWriter::new(File::create(path)?));
+    let body = vec![b'x'; frame_size];
+    let (mut stdout, mut stderr) = (mux.stdout(), mux.stderr());
+    for _ in 0..CAPTURE_SIZE / frame_size / 2 {
+        stdout.write_all(&body)?;
+        stderr.write_all(&body)?;
+    }
+    stdout.flush()
+}
+
+fn decode(input: &Path, output: impl Write) -> io::Result<()> {
+    let mut reader = BufReader::new(File::open(input)?);
+    let mut output = BufWriter::new(output);
+    let mut decoder = DockerStreamDecoder::new();
+    let mut buffer = [0u8; BUFFER_SIZE];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        for chunk in decoder.decode(&buffer[..bytes_read]) {
+            let chunk = chunk.map_err(io::Error::other)?;
+            if chunk.stream_type == StreamType::Stdout as u8 {
+                output.write_all(chunk.body)?;
+            }
+        }
+    }
+    output.flush()
+}
+
+#[cfg(target_os = "linux")]
+fn extract(input: &Path, output: &File) -> io::Result<()> {
+    use docker_stream_codec::ZeroCopyExtractor;
+
+    let input = File::open(input)?;
+    ZeroCopyExtractor::new(&input)
+        .with_destination(StreamType::Stdout, output)
+        .extract()?
+        .expect("file input and output are supported");
+    Ok(())
+}
+
+/// Runs `extract` with the write end of a pipe, which is drained by another
+/// thread.
+#[cfg(target_os = "linux")]
+fn with_pipe(extract: impl FnOnce(File) -> io::Result<()>) -> io::Result<()> {
+    use std::os::fd::FromRawFd;
+
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for both descriptors, which are owned by the
+    // files only.
+    let (mut reader, writer) = unsafe {
+        if libc::pipe(fds.as_mut_ptr()) == -1 {
+            return Err(io::Error::last_os_error());
+        }
+        (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1]))
+    };
+    let drain = std::thread::spawn(move || io::copy(&mut reader, &mut io::sink()));
+    extract(writer)?;
+    drain.join().unwrap()?;
+    Ok(())
+}
+
+fn extraction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extract_stdout");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(CAPTURE_SIZE as u64));
+
+    let output_path = temp_path("output");
+    for frame_size in [4 * 1024, 64 * 1024, 1024 * 1024] {
+        let input = temp_path(&format!("input-{}", frame_size));
+        write_capture(&input, frame_size).unwrap();
+
+        group.bench_with_input(
+            BenchmarkId::new("decoder_to_file", frame_size),
+            &input,
+            |b, input| b.iter(|| decode(input, File::create(&output_path).unwrap()).unwrap()),
+        );
+        #[cfg(target_os = "linux")]
+        {
+            group.bench_with_input(
+                BenchmarkId::new("decoder_to_pipe", frame_size),
+                &input,
+                |b, input| b.iter(|| with_pipe(|output| decode(input, output)).unwrap()),
+            );
+            group.bench_with_input(
+                BenchmarkId::new("zero_copy_to_file", frame_size),
+                &input,
+                |b, input| b.iter(|| extract(input, &File::create(&output_path).unwrap()).unwrap()),
+            );
+            group.bench_with_input(
+                BenchmarkId::new("zero_copy_to_pipe", frame_size),
+                &input,
+                |b, input| b.iter(|| with_pipe(|output| extract(input, &output)).unwrap()),
+            );
+        }
+        std::fs::remove_file(input).unwrap();
+    }
+    let _ = std::fs::remove_file(output_path);
+    group.finish();
+}
+
+criterion_group!(benches, extraction);
+criterion_main!(benches);
@@ -0,0 +1,173 @@
+use std::io::{self, BufRead, Read};
+
+use crate::docker_stream_decoder::DockerStreamDecoder;
+use crate::errors::DockerDecoderError;
+use crate::frame_header::StreamType;
+
+const BUFFER_SIZE: usize = 8192;
+
+/// Pull-based reader of a single stream from a multiplexed docker stream.
+///
+/// Frames of other streams are skipped, so the reader can be used anywhere a
+/// plain [`Read`] or [`BufRead`] is expected:
+///
+/// ```
+/// use std::io::BufRead;
+/// use docker_stream_codec::{DemuxReader, StreamType};
+///
+/// let input: &[u8] = &[
+///     1, 0, 0, 0, 0, 0, 0, 4, b'o', b'n', b'e', b'\n',
+///     2, 0, 0, 0, 0, 0, 0, 4, b'e', b'r', b'r', b'\n',
+///     1, 0, 0, 0, 0, 0, 0, 4, b't', b'w', b'o', b'\n',
+/// ];
+/// let reader = DemuxReader::new(input, StreamType::Stdout);
+/// let lines: Vec<String> = reader.lines().collect::<Result<_, _>>().unwrap();
+/// assert_eq!(lines, ["one", "two"]);
+/// ```
+///
+/// Decoding errors are reported as [`io::ErrorKind::InvalidData`] errors,
+/// after all of the data decoded before them was read. Reading can be
+/// continued after an error, which is useful with a decoder in recovery mode,
+/// see [`DockerStreamDecoder::with_recovery`].
+pub struct DemuxReader<R> {
+    reader: R,
+    decoder: DockerStreamDecoder,
+    stream_type: StreamType,
+
+    input: Box<[u8]>,
+    input_pos: usize,
+    input_len: usize,
+
+    output: Vec<u8>,
+    output_pos: usize,
+
+    error: Option<io::Error>,
+    finished: bool,
+}
+
+impl<R: Read> DemuxReader<R> {
+    pub fn new(reader: R, stream_type: StreamType) -> Self {
+        Self::with_decoder(reader, stream_type, DockerStreamDecoder::new())
+    }
+
+    /// Creates a reader on top of a configured decoder.
+    pub fn with_decoder(reader: R, stream_type: StreamType, decoder: DockerStreamDecoder) -> Self {
+        Self {
+            reader,
+            decoder,
+            stream_type,
+            input: vec![0u8; BUFFER_SIZE].into_boxed_slice(),
+            input_pos: 0,
+            input_len: 0,
+            output: Vec::with_capacity(BUFFER_SIZE),
+            output_pos: 0,
+            error: None,
+            finished: false,
+        }
+    }
+
+    /// Unwraps the underlying reader, dropping any buffered data.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Decodes buffered input until it's exhausted or an error occurs.
+    fn decode_input(&mut self) {
+        let offset_before = self.decoder.offset();
+        let stream_type = self.stream_type as u8;
+        let input = &self.input[self.input_pos..self.input_len];
+        for chunk_result in self.decoder.decode(input) {
+            match chunk_result {
+                Ok(chunk) if chunk.stream_type == stream_type => {
+                    self.output.extend_from_slice(chunk.body)
+                }
+                Ok(_) => {}
+                Err(err) => {
+                    self.error = Some(invalid_data(err));
+                    break;
+                }
+            }
+        }
+        self.input_pos += (self.decoder.offset() - offset_before) as usize;
+    }
+}
+
+fn invalid_data(err: DockerDecoderError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+impl<R: Read> BufRead for DemuxReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.output_pos >= self.output.len() {
+            if let Some(err) = self.error.take() {
+                return Err(err);
+            }
+            if self.finished {
+                break;
+            }
+            self.output.clear();
+            self.output_pos = 0;
+            if self.input_pos >= self.input_len {
+                self.input_pos = 0;
+                self.input_len = self.reader.read(&mut self.input)?;
+                if self.input_len == 0 {
+                    self.finished = true;
+                    self.decoder.finish().map_err(invalid_data)?;
+                    continue;
+                }
+            }
+            self.decode_input();
+        }
+        Ok(&self.output[self.output_pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.output_pos = std::cmp::min(self.output_pos + amt, self.output.len());
+    }
+}
+
+impl<R: Read> Read for DemuxReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n_bytes = std::cmp::min(available.len(), buf.len());
+        buf[..n_bytes].copy_from_slice(&available[..n_bytes]);
+        self.consume(n_bytes);
+        Ok(n_bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::frame_header::frame;
+
+    #[test]
+    fn reads_lines_spanning_frames() {
+        let mut input = frame(1, b"first li");
+        input.extend(frame(2, b"error\n"));
+        input.extend(frame(1, b"ne\nsecond line\n"));
+
+        let reader = DemuxReader::new(input.as_slice(), StreamType::Stdout);
+        let lines: Vec<String> = reader.lines().collect::<Result<_, _>>().unwrap();
+        assert_eq!(lines, ["first line", "second line"]);
+
+        let mut stderr = String::new();
+        DemuxReader::new(input.as_slice(), StreamType::Stderr)
+            .read_to_string(&mut stderr)
+            .unwrap();
+        assert_eq!(stderr, "error\n");
+    }
+
+    #[test]
+    fn reports_errors_after_decoded_data() {
+        let mut input = frame(1, b"data");
+        input.extend_from_slice(&[1, 0, 0, 0, 0, 0]);
+
+        let mut reader = DemuxReader::new(input.as_slice(), StreamType::Stdout);
+        let mut output = Vec::new();
+        let err = reader.read_to_end(&mut output).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(output, b"data");
+        assert_eq!(reader.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+}
@@ -0,0 +1,656 @@
+use std::fmt;
+
+use crate::errors::DockerDecoderError;
+use crate::frame_header::{FrameHeader, FRAME_HEADER_LENGTH};
+
+/// Largest frame length considered plausible while resynchronizing, unless
+/// a maximum frame length is set with [`DockerStreamDecoder::with_max_frame_length`].
+pub const DEFAULT_RESYNC_MAX_LENGTH: u32 = 1024 * 1024;
+
+enum ParsingMode {
+    Header,
+    Body(FrameHeader),
+    /// Scanning for a plausible header after a malformed one.
+    Resync {
+        skipped: u64,
+    },
+}
+
+/// Push-based decoder of a multiplexed docker stream.
+///
+/// Input can be fed in buffers of any size: the decoder keeps partially read
+/// headers and frames between [`DockerStreamDecoder::decode`] calls. Frame
+/// bodies are never copied, chunks borrow the provided buffer instead.
+///
+/// ```
+/// use docker_stream_codec::DockerStreamDecoder;
+///
+/// let mut decoder = DockerStreamDecoder::new();
+/// let input = [1u8, 0, 0, 0, 0, 0, 0, 2, b'h', b'i'];
+/// for chunk in decoder.decode(&input) {
+///     let chunk = chunk.unwrap();
+///     assert_eq!(chunk.stream_type, 1);
+///     assert_eq!(chunk.body, b"hi");
+/// }
+/// ```
+pub struct DockerStreamDecoder {
+    n_bytes_read: u32,
+    header_buffer: [u8; FRAME_HEADER_LENGTH],
+    mode: ParsingMode,
+    /// Number of input bytes consumed so far.
+    offset: u64,
+    /// Index of the current frame, i.e. number of frame headers read so far.
+    frame_index: u64,
+    /// Input offset of the current frame header.
+    frame_offset: u64,
+    /// Position set with [`DockerStreamDecoder::with_position`], restored by
+    /// [`DockerStreamDecoder::finish`].
+    start_offset: u64,
+    start_frame_index: u64,
+    recovery: bool,
+    max_frame_length: Option<u32>,
+    /// Resynchronization to report with the first chunk of the current frame.
+    resynchronized: Option<Resynchronization>,
+}
+
+impl Default for DockerStreamDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> DockerStreamDecoder {
+    pub fn new() -> Self {
+        Self {
+            n_bytes_read: 0,
+            header_buffer: [0u8; FRAME_HEADER_LENGTH],
+            mode: ParsingMode::Header,
+            offset: 0,
+            frame_index: 0,
+            frame_offset: 0,
+            start_offset: 0,
+            start_frame_index: 0,
+            recovery: false,
+            max_frame_length: None,
+            resynchronized: None,
+        }
+    }
+
+    /// Enables or disables recovery after malformed headers.
+    ///
+    /// Without recovery the 8 bytes following a malformed header are treated
+    /// as the next header, so a misaligned stream rarely recovers. With
+    /// recovery the decoder scans forward byte by byte, until it finds a
+    /// plausible header: stream type 0-3, zeroed padding and a length not
+    /// exceeding the maximum frame length (or [`DEFAULT_RESYNC_MAX_LENGTH`],
+    /// if it isn't set). If the candidate's body and
+    /// the next header are already available in the decoded buffer, the next
+    /// header must be plausible as well.
+    ///
+    /// Once decoding resumes, the first chunk of the frame found reports the
+    /// skipped bytes in [`DockerDecoderChunk::resynchronized`].
+    pub fn with_recovery(mut self, recovery: bool) -> Self {
+        self.recovery = recovery;
+        self
+    }
+
+    /// Sets the maximum accepted frame body length.
+    ///
+    /// Headers with a larger length are reported as
+    /// [`DockerDecoderError::FrameTooLarge`] and treated as malformed ones,
+    /// instead of reading up to 4 GiB of the following data as a frame body.
+    pub fn with_max_frame_length(mut self, max_frame_length: Option<u32>) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// Starts counting input offsets and frames from the given position, to
+    /// decode the rest of an input, which beginning is consumed elsewhere.
+    ///
+    /// [`DockerStreamDecoder::finish`] resets the decoder to this position.
+    pub fn with_position(mut self, offset: u64, frame_index: u64) -> Self {
+        self.start_offset = offset;
+        self.start_frame_index = frame_index;
+        self.offset = offset;
+        self.frame_offset = offset;
+        self.frame_index = frame_index;
+        self
+    }
+
+    /// Number of input bytes consumed so far.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Number of frame headers read so far, including rejected ones, which is
+    /// the index of the next frame.
+    pub fn frame_index(&self) -> u64 {
+        self.frame_index
+    }
+
+    /// Checks that input ended on a frame boundary and resets the decoder to
+    /// its starting position, so it can be reused for another input.
+    ///
+    /// Call it at the end of input: a partially read header or frame body is
+    /// reported as [`DockerDecoderError::IncompleteHeader`] or
+    /// [`DockerDecoderError::IncompleteBody`] respectively.
+    pub fn finish(&mut self) -> Result<(), DockerDecoderError> {
+        let result = self.check_finished();
+        self.n_bytes_read = 0;
+        self.mode = ParsingMode::Header;
+        self.offset = self.start_offset;
+        self.frame_index = self.start_frame_index;
+        self.frame_offset = self.start_offset;
+        self.resynchronized = None;
+        result
+    }
+
+    fn check_finished(&self) -> Result<(), DockerDecoderError> {
+        let n_bytes_read = self.n_bytes_read;
+        match self.mode {
+            ParsingMode::Header if n_bytes_read == 0 => Ok(()),
+            ParsingMode::Header => Err(DockerDecoderError::IncompleteHeader {
+                expected: FRAME_HEADER_LENGTH,
+                actual: n_bytes_read as usize,
+                frame_index: self.frame_index,
+                offset: self.offset - n_bytes_read as u64,
+            }),
+            ParsingMode::Body(header) if header.length == 0 => Ok(()),
+            ParsingMode::Body(header) => Err(DockerDecoderError::IncompleteBody {
+                expected: header.length,
+                actual: n_bytes_read,
+                frame_index: self.frame_index,
+                offset: self.frame_offset,
+            }),
+            ParsingMode::Resync { skipped } => {
+                let skipped = skipped + n_bytes_read as u64;
+                Err(DockerDecoderError::UnrecoveredData {
+                    skipped,
+                    offset: self.offset - skipped,
+                })
+            }
+        }
+    }
+
+    /// Returns an iterator over chunks of frame bodies contained in `buffer`.
+    ///
+    /// A frame split between several buffers is returned as several chunks,
+    /// see [`DockerDecoderChunk::is_first`] and [`DockerDecoderChunk::is_last`].
+    /// Frames with an empty body are returned as a single empty chunk.
+    pub fn decode(&'a mut self, buffer: &'a [u8]) -> DockerStreamDecoderChunks<'a> {
+        DockerStreamDecoderChunks {
+            decoder: self,
+            chunk: buffer,
+        }
+    }
+}
+
+/// Iterator returned by [`DockerStreamDecoder::decode`].
+pub struct DockerStreamDecoderChunks<'a> {
+    decoder: &'a mut DockerStreamDecoder,
+    chunk: &'a [u8],
+}
+
+/// Piece of a frame body, borrowed from the decoded buffer.
+pub struct DockerDecoderChunk<'a> {
+    /// Raw stream type from the frame header.
+    pub stream_type: u8,
+    /// Frame body bytes contained in the decoded buffer.
+    pub body: &'a [u8],
+    /// Ordinal number of the frame in the input, starting from 0.
+    pub frame_index: u64,
+    /// Input offset of the frame header.
+    pub frame_offset: u64,
+    /// Frame body length from the frame header.
+    pub frame_length: u32,
+    /// Offset of this chunk within the frame body.
+    pub offset_in_frame: u32,
+    /// Set on the first chunk of a frame, which a decoder in recovery mode
+    /// resumed decoding at, after skipping malformed input.
+    pub resynchronized: Option<Resynchronization>,
+}
+
+/// Malformed input skipped by a decoder in recovery mode, before it found a
+/// plausible header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resynchronization {
+    /// Number of input bytes skipped, starting at the malformed header.
+    pub skipped: u64,
+    /// Input offset decoding resumed at, i.e. of the header found.
+    pub offset: u64,
+}
+
+impl fmt::Display for Resynchronization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Skipped {} bytes of malformed data, decoding resumed at offset {}",
+            self.skipped, self.offset
+        )
+    }
+}
+
+impl DockerDecoderChunk<'_> {
+    /// Checks if the chunk starts the frame body.
+    pub fn is_first(&self) -> bool {
+        self.offset_in_frame == 0
+    }
+
+    /// Checks if the chunk ends the frame body.
+    pub fn is_last(&self) -> bool {
+        self.offset_in_frame as usize + self.body.len() == self.frame_length as usize
+    }
+
+    /// Input offset of the chunk's first byte.
+    pub fn offset(&self) -> u64 {
+        self.frame_offset + FRAME_HEADER_LENGTH as u64 + self.offset_in_frame as u64
+    }
+}
+
+impl<'a> DockerStreamDecoderChunks<'a> {
+    /// Checks if `buffer` looks like a genuine frame header.
+    fn plausible_header(&self, buffer: &[u8]) -> Option<FrameHeader> {
+        let header = FrameHeader::parse(buffer[..FRAME_HEADER_LENGTH].try_into().ok()?).ok()?;
+        let max_length = self
+            .decoder
+            .max_frame_length
+            .unwrap_or(DEFAULT_RESYNC_MAX_LENGTH);
+        if header.stream_type > 3 || header.length > max_length {
+            return None;
+        }
+        Some(header)
+    }
+
+    /// Parses the collected header, checking its length against the limit.
+    fn parse_header(&self) -> Result<FrameHeader, DockerDecoderError> {
+        let offset = self.decoder.offset - FRAME_HEADER_LENGTH as u64;
+        let frame_index = self.decoder.frame_index;
+        let header = FrameHeader::parse(&self.decoder.header_buffer).map_err(|_| {
+            DockerDecoderError::MalformedHeader {
+                header: self.decoder.header_buffer,
+                frame_index,
+                offset,
+            }
+        })?;
+        match self.decoder.max_frame_length {
+            Some(max_length) if header.length > max_length => {
+                Err(DockerDecoderError::FrameTooLarge {
+                    length: header.length,
+                    frame_index,
+                    offset,
+                })
+            }
+            _ => Ok(header),
+        }
+    }
+
+    /// Copies available input into the header buffer, returns true once it's full.
+    fn fill_header(&mut self) -> bool {
+        let n_bytes_read = self.decoder.n_bytes_read as usize;
+        let bytes_to_copy = std::cmp::min(FRAME_HEADER_LENGTH - n_bytes_read, self.chunk.len());
+        self.decoder.header_buffer[n_bytes_read..n_bytes_read + bytes_to_copy]
+            .copy_from_slice(&self.chunk[0..bytes_to_copy]);
+
+        self.chunk = &self.chunk[bytes_to_copy..];
+        self.decoder.offset += bytes_to_copy as u64;
+        self.decoder.n_bytes_read += bytes_to_copy as u32;
+        self.decoder.n_bytes_read >= FRAME_HEADER_LENGTH as u32
+    }
+
+    fn start_frame(&mut self, header: FrameHeader) {
+        self.decoder.n_bytes_read = 0;
+        self.decoder.frame_offset = self.decoder.offset - FRAME_HEADER_LENGTH as u64;
+        self.decoder.mode = ParsingMode::Body(header);
+    }
+
+    /// Returns the next piece of the current frame body.
+    fn read_body(&mut self, header: FrameHeader) -> DockerDecoderChunk<'a> {
+        let offset_in_frame = self.decoder.n_bytes_read;
+        let bytes_to_read =
+            std::cmp::min((header.length - offset_in_frame) as usize, self.chunk.len());
+        self.decoder.n_bytes_read += bytes_to_read as u32;
+        self.decoder.offset += bytes_to_read as u64;
+        let body = &self.chunk[0..bytes_to_read];
+        self.chunk = &self.chunk[bytes_to_read..];
+
+        let chunk = DockerDecoderChunk {
+            stream_type: header.stream_type,
+            body,
+            frame_index: self.decoder.frame_index,
+            frame_offset: self.decoder.frame_offset,
+            frame_length: header.length,
+            offset_in_frame,
+            resynchronized: self.decoder.resynchronized.take(),
+        };
+        if self.decoder.n_bytes_read >= header.length {
+            self.decoder.n_bytes_read = 0;
+            self.decoder.frame_index += 1;
+            self.decoder.mode = ParsingMode::Header;
+        }
+        chunk
+    }
+
+    /// Checks the header following the candidate frame, if it's already available.
+    fn confirm_candidate(&self, candidate: &FrameHeader) -> bool {
+        let next_header_start = candidate.length as usize;
+        if self.chunk.len() < next_header_start + FRAME_HEADER_LENGTH {
+            return true;
+        }
+        self.plausible_header(&self.chunk[next_header_start..])
+            .is_some()
+    }
+}
+
+impl<'a> Iterator for DockerStreamDecoderChunks<'a> {
+    type Item = Result<DockerDecoderChunk<'a>, DockerDecoderError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.decoder.mode {
+                // Empty frames are returned even if there's no more input
+                ParsingMode::Body(header) if header.length == 0 || !self.chunk.is_empty() => {
+                    return Some(Ok(self.read_body(header)));
+                }
+                _ if self.chunk.is_empty() => return None,
+                ParsingMode::Header => {
+                    if !self.fill_header() {
+                        continue;
+                    }
+                    match self.parse_header() {
+                        Ok(header) => self.start_frame(header),
+                        Err(err) => {
+                            // A rejected header still takes its frame's index
+                            self.decoder.frame_index += 1;
+                            if self.decoder.recovery {
+                                self.decoder.mode = ParsingMode::Resync { skipped: 0 };
+                            } else {
+                                self.decoder.n_bytes_read = 0;
+                            }
+                            return Some(Err(err));
+                        }
+                    }
+                }
+                ParsingMode::Resync { skipped } => {
+                    let mut skipped = skipped;
+                    if self.decoder.n_bytes_read >= FRAME_HEADER_LENGTH as u32 {
+                        // Current window was rejected, sliding it by one byte
+                        self.decoder.header_buffer.copy_within(1.., 0);
+                        self.decoder.n_bytes_read -= 1;
+                        skipped += 1;
+                        self.decoder.mode = ParsingMode::Resync { skipped };
+                    }
+                    if !self.fill_header() {
+                        continue;
+                    }
+                    let candidate = self
+                        .plausible_header(&self.decoder.header_buffer)
+                        .filter(|header| self.confirm_candidate(header));
+                    if let Some(header) = candidate {
+                        self.start_frame(header);
+                        self.decoder.resynchronized = Some(Resynchronization {
+                            skipped,
+                            offset: self.decoder.frame_offset,
+                        });
+                    }
+                }
+                ParsingMode::Body(_) => unreachable!("Body with available input is handled above"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::frame_header::frame;
+
+    /// Stream types and bodies of decoded frames, consecutive ones of the same
+    /// stream merged.
+    type Bodies = Vec<(u8, Vec<u8>)>;
+
+    /// Decodes input fed in pieces of `step` bytes, collecting bodies, errors
+    /// and resynchronizations.
+    fn decode_by(
+        decoder: &mut DockerStreamDecoder,
+        input: &[u8],
+        step: usize,
+    ) -> (Bodies, Vec<DockerDecoderError>, Vec<Resynchronization>) {
+        let mut bodies: Bodies = Vec::new();
+        let mut errors = Vec::new();
+        let mut resyncs = Vec::new();
+        for piece in input.chunks(step) {
+            for result in decoder.decode(piece) {
+                if let Ok(DockerDecoderChunk {
+                    resynchronized: Some(resync),
+                    ..
+                }) = result
+                {
+                    resyncs.push(resync);
+                }
+                match result {
+                    Ok(chunk) => match bodies.last_mut() {
+                        Some((stream_type, body)) if *stream_type == chunk.stream_type => {
+                            body.extend_from_slice(chunk.body)
+                        }
+                        _ => bodies.push((chunk.stream_type, chunk.body.to_vec())),
+                    },
+                    Err(err) => errors.push(err),
+                }
+            }
+        }
+        (bodies, errors, resyncs)
+    }
+
+    #[test]
+    fn decodes_frames_split_between_buffers() {
+        let mut input = frame(1, b"hello");
+        input.extend(frame(2, b"world"));
+        for step in [1, 3, 8, 100] {
+            let mut decoder = DockerStreamDecoder::new();
+            let (bodies, errors, resyncs) = decode_by(&mut decoder, &input, step);
+            assert!(errors.is_empty() && resyncs.is_empty());
+            assert_eq!(bodies, vec![(1, b"hello".to_vec()), (2, b"world".to_vec())]);
+            assert_eq!(decoder.offset(), input.len() as u64);
+        }
+    }
+
+    #[test]
+    fn reports_chunk_positions() {
+        let mut input = frame(1, b"hello");
+        input.extend(frame(2, b""));
+        input.extend(frame(1, b"ab"));
+
+        let mut decoder = DockerStreamDecoder::new();
+        let mut positions = Vec::new();
+        for piece in input.chunks(4) {
+            for chunk in decoder.decode(piece) {
+                let chunk = chunk.unwrap();
+                positions.push((
+                    chunk.frame_index,
+                    chunk.frame_offset,
+                    chunk.offset_in_frame,
+                    chunk.body.len(),
+                    chunk.is_first(),
+                    chunk.is_last(),
+                ));
+            }
+        }
+        assert_eq!(
+            positions,
+            vec![
+                (0, 0, 0, 4, true, false),
+                (0, 0, 4, 1, false, true),
+                (1, 13, 0, 0, true, true),
+                (2, 21, 0, 2, true, true),
+            ]
+        );
+        assert_eq!(decoder.frame_index(), 3);
+    }
+
+    #[test]
+    fn resynchronizes_after_malformed_header() {
+        let mut input = frame(1, b"first");
+        input.extend_from_slice(&[0x01, 0xff, 0x13, 0x00, 0x00, 0x00, 0x07, 0x42, 0x00]);
+        let resumed_at = input.len() as u64;
+        input.extend(frame(2, b"second"));
+        input.extend(frame(1, b"third"));
+
+        for step in [1, 5, 100] {
+            let mut decoder = DockerStreamDecoder::new().with_recovery(true);
+            let (bodies, errors, resyncs) = decode_by(&mut decoder, &input, step);
+            assert_eq!(
+                bodies,
+                vec![
+                    (1, b"first".to_vec()),
+                    (2, b"second".to_vec()),
+                    (1, b"third".to_vec())
+                ]
+            );
+            assert_eq!(errors.len(), 1);
+            assert!(matches!(
+                errors[0],
+                DockerDecoderError::MalformedHeader {
+                    frame_index: 1,
+                    offset: 13,
+                    ..
+                }
+            ));
+            assert_eq!(
+                resyncs,
+                vec![Resynchronization {
+                    skipped: 9,
+                    offset: resumed_at
+                }]
+            );
+        }
+    }
+
+    #[test]
+    fn rejected_headers_take_frame_indices() {
+        let mut input = frame(1, b"zero");
+        input.extend_from_slice(&[0x01, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        input.extend(frame(2, b"two"));
+        input.extend(frame(1, b"three"));
+        input.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff]);
+        input.extend(frame(1, b"five"));
+
+        let mut decoder = DockerStreamDecoder::new()
+            .with_recovery(true)
+            .with_max_frame_length(Some(16));
+        let mut frames = Vec::new();
+        let mut errors = Vec::new();
+        for result in decoder.decode(&input) {
+            match result {
+                Ok(chunk) => frames.push((chunk.frame_index, chunk.frame_offset)),
+                Err(err) => errors.push(err),
+            }
+        }
+        assert_eq!(frames, [(0, 0), (2, 20), (3, 31), (5, 52)]);
+        assert!(matches!(
+            errors[..],
+            [
+                DockerDecoderError::MalformedHeader {
+                    frame_index: 1,
+                    offset: 12,
+                    ..
+                },
+                DockerDecoderError::FrameTooLarge {
+                    frame_index: 4,
+                    offset: 44,
+                    ..
+                }
+            ]
+        ));
+        assert_eq!(decoder.frame_index(), 6);
+    }
+
+    #[test]
+    fn resync_rejects_candidate_followed_by_garbage() {
+        // [2, 0, 0, 0, 0, 0, 0, 1] is a plausible header, but the next one isn't
+        let mut input = vec![0xffu8; FRAME_HEADER_LENGTH];
+        input.extend_from_slice(&[0x02, 0, 0, 0, 0, 0, 0, 1, 0xaa]);
+        input.extend_from_slice(&[0xff; FRAME_HEADER_LENGTH]);
+        let resumed_at = input.len() as u64;
+        // Long enough for every candidate inside the rejected one to be checked
+        let body = vec![b'x'; 200_000];
+        input.extend(frame(1, &body));
+        input.extend(frame(1, b""));
+
+        let mut decoder = DockerStreamDecoder::new().with_recovery(true);
+        let (bodies, errors, resyncs) = decode_by(&mut decoder, &input, input.len());
+        assert_eq!(bodies, vec![(1, body)]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(resyncs.len(), 1);
+        assert_eq!(resyncs[0].offset, resumed_at);
+    }
+
+    #[test]
+    fn finish_reports_truncated_input() {
+        let input = frame(1, b"hello");
+
+        let mut decoder = DockerStreamDecoder::new();
+        decode_by(&mut decoder, &input[..5], 5);
+        assert!(matches!(
+            decoder.finish(),
+            Err(DockerDecoderError::IncompleteHeader {
+                expected: FRAME_HEADER_LENGTH,
+                actual: 5,
+                frame_index: 0,
+                offset: 0,
+            })
+        ));
+
+        decode_by(&mut decoder, &input[..10], 10);
+        assert!(matches!(
+            decoder.finish(),
+            Err(DockerDecoderError::IncompleteBody {
+                expected: 5,
+                actual: 2,
+                frame_index: 0,
+                offset: 0,
+            })
+        ));
+
+        decode_by(&mut decoder, &input, 10);
+        assert!(decoder.finish().is_ok());
+    }
+
+    #[test]
+    fn finish_restores_starting_position() {
+        let input = frame(1, b"hello");
+        let mut decoder = DockerStreamDecoder::new().with_position(100, 5);
+        for _ in 0..2 {
+            let positions: Vec<_> = decoder
+                .decode(&input)
+                .map(|chunk| {
+                    let chunk = chunk.unwrap();
+                    (chunk.frame_index, chunk.frame_offset)
+                })
+                .collect();
+            assert_eq!(positions, [(5, 100)]);
+            assert!(decoder.finish().is_ok());
+            assert_eq!((decoder.offset(), decoder.frame_index()), (100, 5));
+        }
+    }
+
+    #[test]
+    fn rejects_frames_exceeding_max_length() {
+        let mut input = frame(1, b"ok");
+        let oversized_at = input.len() as u64;
+        input.extend_from_slice(&[0x01, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff]);
+        input.extend(frame(2, b"after"));
+
+        let mut decoder = DockerStreamDecoder::new()
+            .with_recovery(true)
+            .with_max_frame_length(Some(16));
+        let (bodies, errors, resyncs) = decode_by(&mut decoder, &input, 3);
+        assert_eq!(bodies, vec![(1, b"ok".to_vec()), (2, b"after".to_vec())]);
+        assert!(matches!(
+            errors[0],
+            DockerDecoderError::FrameTooLarge { length: 0xffff_ffff, frame_index: 1, offset } if offset == oversized_at
+        ));
+        assert_eq!(errors.len(), 1);
+        assert_eq!(resyncs.len(), 1);
+        assert_eq!(resyncs[0].skipped, 8);
+    }
+}
@@ -0,0 +1,672 @@
+use byteorder::{BigEndian, ByteOrder};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use std::io::{self, Read};
+
+use crate::fault_injection::{FaultInjection, FaultKind, InjectedFault};
+use crate::frame_header::{FrameHeader, FRAME_HEADER_LENGTH};
+use crate::source_scheduler::{RandomScheduler, ScheduledSource, SourceScheduler};
+
+/// Source of data for one of the multiplexed streams.
+pub struct StreamSourceInfo {
+    /// Raw stream type written into headers of frames read from this source.
+    pub stream_type: u8,
+    pub source: Box<dyn Read>,
+    /// Number of bytes in the source, if known, e.g. the length of a file.
+    /// Frame bodies of sources of known size are streamed straight from them.
+    pub size: Option<u64>,
+}
+
+/// How [`DockerStreamMultiplexer`] splits source data into frames.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FramingMode {
+    /// Frames of random size in the `frame_min..=frame_max` range, cut at
+    /// arbitrary bytes.
+    #[default]
+    Bytes,
+    /// The given number of lines per frame, at least one. Lines longer than
+    /// `frame_max` are split into several frames.
+    Lines(u32),
+    /// Frames of random size in the `frame_min..=frame_max` range, cut after
+    /// the last newline within it, unless there's none.
+    LineBreak,
+}
+
+/// Where the body of the current frame comes from.
+#[derive(Clone, Copy)]
+enum BodySource {
+    /// The body buffer
+    Buffer,
+    /// The source with the given index, read while the body is copied
+    Source(usize),
+    /// Zero bytes
+    Zeros,
+}
+
+#[derive(Clone, Copy)]
+enum OperationMode {
+    Read,
+    CopyHeader(usize),
+    CopyBody(usize),
+}
+
+/// Mock multiplexed docker stream, built from several sources.
+///
+/// Implements [`Read`], producing frames with bodies of random size in the
+/// `frame_min..=frame_max` range, each one read from a source picked by its
+/// [`SourceScheduler`], at random by default, until all of the sources are
+/// exhausted.
+///
+/// Frame sizes can be up to `u32::MAX` bytes. Bodies are read from sources of
+/// known size as they are copied to the output, so memory use doesn't depend
+/// on the frame size. Frames of sources of unknown size and line-aligned
+/// frames are buffered instead, and are at most
+/// [`DockerStreamMultiplexer::BUFFERED_FRAME_MAX`] bytes.
+///
+/// Frames can be aligned to lines, see [`FramingMode`], and faults can be
+/// injected into them, see [`FaultInjection`].
+///
+/// Uses the thread RNG by default; use [`DockerStreamMultiplexer::with_seed`]
+/// to generate the same stream from the same sources on every run.
+pub struct DockerStreamMultiplexer {
+    operation_mode: OperationMode,
+    body_source: BodySource,
+    body_buffer: Vec<u8>,
+    /// Room for a duplicated header
+    header_buffer: [u8; 2 * FRAME_HEADER_LENGTH],
+    header_length: usize,
+
+    frame_min: u32,
+    frame_max: u32,
+    sources: Vec<StreamSourceInfo>,
+    /// Scheduler view of `sources`
+    scheduled: Vec<ScheduledSource>,
+    scheduler: Box<dyn SourceScheduler>,
+    /// Data read ahead from `sources` for line-aligned framing
+    pending: Vec<Vec<u8>>,
+    framing: FramingMode,
+    rand_rng: Box<dyn RngCore>,
+    faults: FaultInjection,
+    injected_faults: Vec<InjectedFault>,
+
+    bytes_written: usize,
+    body_length: usize,
+    /// Position in the body and mask of a bit flip, applied while copying it
+    body_bit_flip: Option<(usize, u8)>,
+    /// Number of frames generated so far
+    frame_index: u64,
+    /// Number of bytes output so far
+    offset: u64,
+    last_stream_type: u8,
+    /// Sources are exhausted and the final faults are injected
+    finished: bool,
+}
+
+impl DockerStreamMultiplexer {
+    /// Largest frame body of sources of unknown size and of line-aligned
+    /// frames, which are buffered. Such frames are clamped to it, even if
+    /// `frame_max` is larger; `docker-stream-encoder` rejects a larger
+    /// `--frame-max` for them instead.
+    pub const BUFFERED_FRAME_MAX: u32 = 1024 * 1024;
+
+    /// Creates a multiplexer with frame bodies of `frame_min..=frame_max` bytes.
+    pub fn new(sources: Vec<StreamSourceInfo>, frame_max: u32, frame_min: u32) -> Self {
+        Self {
+            operation_mode: OperationMode::Read,
+            body_source: BodySource::Buffer,
+            body_buffer: Vec::new(),
+            header_buffer: [0u8; 2 * FRAME_HEADER_LENGTH],
+            header_length: FRAME_HEADER_LENGTH,
+            frame_max,
+            frame_min,
+            scheduled: sources
+                .iter()
+                .enumerate()
+                .map(|(index, source)| ScheduledSource {
+                    index,
+                    stream_type: source.stream_type,
+                    bytes_read: 0,
+                })
+                .collect(),
+            pending: sources.iter().map(|_| Vec::new()).collect(),
+            sources,
+            scheduler: Box::new(RandomScheduler),
+            framing: FramingMode::Bytes,
+            rand_rng: Box::new(rand::thread_rng()),
+            faults: FaultInjection::default(),
+            injected_faults: Vec::new(),
+            bytes_written: 0,
+            body_length: 0,
+            body_bit_flip: None,
+            frame_index: 0,
+            offset: 0,
+            last_stream_type: 1,
+            finished: false,
+        }
+    }
+
+    /// Uses ChaCha8 RNG seeded with `seed`, producing the same output on any
+    /// platform.
+    pub fn with_seed(self, seed: u64) -> Self {
+        self.with_rng(ChaCha8Rng::seed_from_u64(seed))
+    }
+
+    /// Uses `rng` for frame sizes and source selection.
+    pub fn with_rng(mut self, rng: impl RngCore + 'static) -> Self {
+        self.rand_rng = Box::new(rng);
+        self
+    }
+
+    /// Uses `scheduler` to pick the source of each frame.
+    pub fn with_scheduler(mut self, scheduler: impl SourceScheduler + 'static) -> Self {
+        self.scheduler = Box::new(scheduler);
+        self
+    }
+
+    /// Uses `framing` to split source data into frames.
+    pub fn with_framing(mut self, framing: FramingMode) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Injects `faults` into generated frames.
+    pub fn with_faults(mut self, faults: FaultInjection) -> Self {
+        self.faults = faults;
+        self
+    }
+
+    /// Faults injected so far, in output order.
+    pub fn injected_faults(&self) -> &[InjectedFault] {
+        &self.injected_faults
+    }
+
+    fn get_random_chunk_size(&mut self) -> usize {
+        if self.frame_min == self.frame_max {
+            return self.frame_max as usize;
+        }
+        self.rand_rng.gen_range(self.frame_min..=self.frame_max) as usize
+    }
+
+    /** Picks a scheduled source for a new chunk and generates its header */
+    fn read_chunk(&mut self) -> io::Result<Option<FrameHeader>> {
+        while !self.sources.is_empty() {
+            let bytes_to_read = match self.framing {
+                FramingMode::Lines(_) => self.frame_max as usize,
+                FramingMode::Bytes | FramingMode::LineBreak => self.get_random_chunk_size(),
+            };
+            let source_index = self
+                .scheduler
+                .next_source(&self.scheduled, &mut *self.rand_rng);
+
+            let remaining = self.sources[source_index]
+                .size
+                .map(|size| size.saturating_sub(self.scheduled[source_index].bytes_read));
+            self.body_source = BodySource::Buffer;
+            let n_bytes_read = match (self.framing, remaining) {
+                (FramingMode::Bytes, Some(remaining)) => {
+                    self.body_source = BodySource::Source(source_index);
+                    std::cmp::min(bytes_to_read as u64, remaining) as usize
+                }
+                (FramingMode::Bytes, None) => {
+                    let limit = std::cmp::min(bytes_to_read, Self::BUFFERED_FRAME_MAX as usize);
+                    self.reserve_body_buffer(limit);
+                    self.sources[source_index]
+                        .source
+                        .read(&mut self.body_buffer[..limit])?
+                }
+                _ => self.read_lines(
+                    source_index,
+                    std::cmp::min(bytes_to_read, Self::BUFFERED_FRAME_MAX as usize),
+                )?,
+            };
+
+            if n_bytes_read == 0 {
+                self.sources.remove(source_index);
+                self.scheduled.remove(source_index);
+                self.pending.remove(source_index);
+            } else {
+                self.scheduled[source_index].bytes_read += n_bytes_read as u64;
+                return Ok(Some(FrameHeader::new(
+                    self.sources[source_index].stream_type,
+                    n_bytes_read as u32,
+                )));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Reads up to `limit` bytes of a source into the body buffer, cutting
+    /// them at a line end according to the framing mode, and keeping the rest
+    /// for the following frames.
+    fn read_lines(&mut self, source_index: usize, limit: usize) -> io::Result<usize> {
+        self.reserve_body_buffer(limit);
+        let pending = &mut self.pending[source_index];
+        let mut filled = std::cmp::min(pending.len(), limit);
+        self.body_buffer[..filled].copy_from_slice(&pending[..filled]);
+        pending.drain(..filled);
+        while filled < limit {
+            let n_bytes_read = self.sources[source_index]
+                .source
+                .read(&mut self.body_buffer[filled..limit])?;
+            if n_bytes_read == 0 {
+                break;
+            }
+            filled += n_bytes_read;
+        }
+
+        let body = &self.body_buffer[..filled];
+        let mut line_ends = body
+            .iter()
+            .enumerate()
+            .filter(|(_, &c)| c == b'\n')
+            .map(|(position, _)| position + 1);
+        let frame_length = match self.framing {
+            FramingMode::Lines(lines) => line_ends.nth(std::cmp::max(lines, 1) as usize - 1),
+            _ => line_ends.next_back(),
+        }
+        .unwrap_or(filled);
+        pending.splice(0..0, body[frame_length..].iter().copied());
+        Ok(frame_length)
+    }
+
+    fn reserve_body_buffer(&mut self, length: usize) {
+        if self.body_buffer.len() < length {
+            self.body_buffer.resize(length, 0);
+        }
+    }
+
+    /// Serializes the header of a frame, injecting faults into it.
+    fn start_frame(&mut self, header: FrameHeader) {
+        let frame_index = self.frame_index;
+        let frame_offset = self.offset;
+        self.frame_index += 1;
+        self.last_stream_type = header.stream_type;
+        self.body_length = header.length as usize;
+        self.body_bit_flip = None;
+        self.header_length = FRAME_HEADER_LENGTH;
+        header.serialize(&mut self.header_buffer);
+
+        for kind in self.faults.pick(frame_index, &mut *self.rand_rng) {
+            let detail = match kind {
+                FaultKind::NonZeroPadding => {
+                    let position = self.rand_rng.gen_range(1..=3);
+                    let value = self.rand_rng.gen_range(1..=u8::MAX);
+                    self.header_buffer[position] = value;
+                    format!("padding byte {} is {:#04x}", position, value)
+                }
+                FaultKind::UnknownStreamType => {
+                    let stream_type = self.rand_rng.gen_range(3..=u8::MAX);
+                    self.header_buffer[0] = stream_type;
+                    format!("stream type {}", stream_type)
+                }
+                FaultKind::OverstatedLength | FaultKind::UnderstatedLength => {
+                    let length = self.body_length as u32;
+                    let stated = if kind == FaultKind::OverstatedLength {
+                        length
+                            .saturating_add(self.rand_rng.gen_range(1..=FRAME_HEADER_LENGTH as u32))
+                    } else {
+                        self.rand_rng.gen_range(0..length)
+                    };
+                    BigEndian::write_u32(&mut self.header_buffer[4..FRAME_HEADER_LENGTH], stated);
+                    format!("length {} instead of {}", stated, length)
+                }
+                FaultKind::DuplicatedHeader => {
+                    self.header_buffer
+                        .copy_within(..FRAME_HEADER_LENGTH, FRAME_HEADER_LENGTH);
+                    self.header_length = 2 * FRAME_HEADER_LENGTH;
+                    "header written twice".to_string()
+                }
+                FaultKind::BitFlip => {
+                    let position = self
+                        .rand_rng
+                        .gen_range(0..self.header_length + self.body_length);
+                    let bit = self.rand_rng.gen_range(0..8);
+                    match position.checked_sub(self.header_length) {
+                        None => self.header_buffer[position] ^= 1 << bit,
+                        Some(body_position) => self.body_bit_flip = Some((body_position, 1 << bit)),
+                    }
+                    self.injected_faults.push(InjectedFault {
+                        kind,
+                        frame_index,
+                        offset: frame_offset + position as u64,
+                        detail: format!("bit {} flipped", bit),
+                    });
+                    continue;
+                }
+                FaultKind::TruncatedFinalFrame => unreachable!("injected on finish"),
+            };
+            self.injected_faults.push(InjectedFault {
+                kind,
+                frame_index,
+                offset: frame_offset,
+                detail,
+            });
+        }
+        self.operation_mode = OperationMode::CopyHeader(0);
+    }
+
+    /// Adds a frame, which body is cut in half, if it's requested.
+    fn finish(&mut self) {
+        self.finished = true;
+        if !self.faults.truncate(&mut *self.rand_rng) {
+            return;
+        }
+        let length = std::cmp::max(self.get_random_chunk_size(), 2);
+        self.injected_faults.push(InjectedFault {
+            kind: FaultKind::TruncatedFinalFrame,
+            frame_index: self.frame_index,
+            offset: self.offset,
+            detail: format!("{} of {} body bytes", length / 2, length),
+        });
+        self.frame_index += 1;
+        FrameHeader::new(self.last_stream_type, length as u32).serialize(&mut self.header_buffer);
+        self.header_length = FRAME_HEADER_LENGTH;
+        self.body_length = length / 2;
+        self.body_bit_flip = None;
+        self.body_source = BodySource::Zeros;
+        self.operation_mode = OperationMode::CopyHeader(0);
+    }
+
+    fn copy_header(&mut self, buf: &mut [u8], header_bytes_written: usize) {
+        let remainder = self.header_length - header_bytes_written;
+        let bytes_to_write = std::cmp::min(remainder, buf.len() - self.bytes_written);
+
+        let dest_buf_rng = self.bytes_written..self.bytes_written + bytes_to_write;
+        let body_buf_rng = header_bytes_written..header_bytes_written + bytes_to_write;
+
+        buf[dest_buf_rng].copy_from_slice(&self.header_buffer[body_buf_rng]);
+        self.bytes_written += bytes_to_write;
+        self.offset += bytes_to_write as u64;
+
+        if bytes_to_write < remainder {
+            self.operation_mode = OperationMode::CopyHeader(header_bytes_written + bytes_to_write);
+        } else {
+            self.operation_mode = OperationMode::CopyBody(0);
+        }
+    }
+
+    fn copy_body(&mut self, buf: &mut [u8], body_bytes_written: usize) -> io::Result<()> {
+        let remainder = self.body_length - body_bytes_written;
+        let space = std::cmp::min(remainder, buf.len() - self.bytes_written);
+        let dest_buf = &mut buf[self.bytes_written..self.bytes_written + space];
+
+        let bytes_to_write = match self.body_source {
+            BodySource::Buffer => {
+                dest_buf.copy_from_slice(
+                    &self.body_buffer[body_bytes_written..body_bytes_written + space],
+                );
+                space
+            }
+            BodySource::Source(source_index) => {
+                let n_bytes_read = self.sources[source_index].source.read(dest_buf)?;
+                if n_bytes_read == 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "stream source ended before its specified size",
+                    ));
+                }
+                n_bytes_read
+            }
+            BodySource::Zeros => {
+                dest_buf.fill(0);
+                space
+            }
+        };
+        if let Some((position, mask)) = self.body_bit_flip {
+            if (body_bytes_written..body_bytes_written + bytes_to_write).contains(&position) {
+                dest_buf[position - body_bytes_written] ^= mask;
+            }
+        }
+        self.bytes_written += bytes_to_write;
+        self.offset += bytes_to_write as u64;
+
+        if bytes_to_write < remainder {
+            self.operation_mode = OperationMode::CopyBody(body_bytes_written + bytes_to_write);
+        } else {
+            self.operation_mode = OperationMode::Read;
+        }
+        Ok(())
+    }
+
+    /// Returns the bytes written before a source failed, if any. The state is
+    /// kept, so the failed read is retried on the next call.
+    fn interrupted(&self, err: io::Error) -> io::Result<usize> {
+        match self.bytes_written {
+            0 => Err(err),
+            bytes_written => Ok(bytes_written),
+        }
+    }
+}
+
+impl Read for DockerStreamMultiplexer {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.bytes_written = 0;
+
+        while self.bytes_written < buf.len() {
+            match self.operation_mode {
+                OperationMode::Read => match self.read_chunk() {
+                    Ok(Some(header)) => self.start_frame(header),
+                    Ok(None) if !self.finished => self.finish(),
+                    Ok(None) => break,
+                    Err(err) => return self.interrupted(err),
+                },
+                OperationMode::CopyHeader(header_bytes_written) => {
+                    self.copy_header(buf, header_bytes_written)
+                }
+                OperationMode::CopyBody(body_bytes_written) => {
+                    if let Err(err) = self.copy_body(buf, body_bytes_written) {
+                        return self.interrupted(err);
+                    }
+                }
+            }
+        }
+        Ok(self.bytes_written)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+
+    fn make_simple_input_output() -> (Vec<StreamSourceInfo>, Vec<u8>) {
+        let test_input: Cursor<[u8; 9]> =
+            Cursor::new([0x01u8, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09]);
+        let sources_list = vec![{
+            StreamSourceInfo {
+                stream_type: 2u8,
+                source: Box::new(test_input),
+                size: None,
+            }
+        }];
+
+        let header: [u8; FRAME_HEADER_LENGTH] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03];
+        let mut expected_output = Vec::<u8>::new();
+        expected_output.extend_from_slice(&header);
+        expected_output.extend_from_slice(&[0x01, 0x02, 0x03]);
+        expected_output.extend_from_slice(&header);
+        expected_output.extend_from_slice(&[0x04, 0x05, 0x06]);
+        expected_output.extend_from_slice(&header);
+        expected_output.extend_from_slice(&[0x07, 0x08, 0x09]);
+
+        (sources_list, expected_output)
+    }
+
+    #[test]
+    fn breaks_single_stream_into_chunks() {
+        let (test_source, expected_output) = make_simple_input_output();
+        let mut mp = DockerStreamMultiplexer::new(test_source, 3, 3);
+
+        let mut output = vec![0; expected_output.len()];
+        let n_bytes_read = mp.read(&mut output).unwrap();
+
+        assert_eq!(n_bytes_read, expected_output.len());
+        assert_eq!(output, expected_output);
+    }
+
+    #[test]
+    fn same_seed_produces_same_stream() {
+        let generate = |seed| {
+            let sources = (0..3u8)
+                .map(|stream_type| StreamSourceInfo {
+                    stream_type,
+                    source: Box::new(Cursor::new(vec![stream_type; 1000])),
+                    size: None,
+                })
+                .collect();
+            let mut output = Vec::new();
+            DockerStreamMultiplexer::new(sources, 50, 1)
+                .with_seed(seed)
+                .read_to_end(&mut output)
+                .unwrap();
+            output
+        };
+        assert_eq!(generate(42), generate(42));
+        assert_ne!(generate(42), generate(43));
+    }
+
+    #[test]
+    fn aligns_frames_to_lines() {
+        let frames = |framing, frame_max| {
+            let sources = vec![StreamSourceInfo {
+                stream_type: 1,
+                source: Box::new(Cursor::new(b"one\ntwo\nthree\nfour".to_vec())),
+                size: None,
+            }];
+            let mut output = Vec::new();
+            DockerStreamMultiplexer::new(sources, frame_max, frame_max)
+                .with_framing(framing)
+                .read_to_end(&mut output)
+                .unwrap();
+            let mut bodies = Vec::new();
+            while !output.is_empty() {
+                let length = output[7] as usize;
+                bodies.push(String::from_utf8(output[8..8 + length].to_vec()).unwrap());
+                output.drain(..8 + length);
+            }
+            bodies
+        };
+        assert_eq!(
+            frames(FramingMode::Lines(1), 100),
+            ["one\n", "two\n", "three\n", "four"]
+        );
+        assert_eq!(
+            frames(FramingMode::Lines(2), 100),
+            ["one\ntwo\n", "three\nfour"]
+        );
+        assert_eq!(
+            frames(FramingMode::Lines(1), 4),
+            ["one\n", "two\n", "thre", "e\n", "four"]
+        );
+        assert_eq!(
+            frames(FramingMode::LineBreak, 10),
+            ["one\ntwo\n", "three\n", "four"]
+        );
+    }
+
+    #[test]
+    fn injected_faults_are_reported_by_decoder() {
+        use crate::{DockerDecoderError, DockerStreamDecoder};
+
+        let generate = |faults| {
+            let sources = vec![StreamSourceInfo {
+                stream_type: 1,
+                source: Box::new(Cursor::new(vec![b'x'; 40])),
+                size: Some(40),
+            }];
+            let mut multiplexer = DockerStreamMultiplexer::new(sources, 10, 10)
+                .with_seed(1)
+                .with_faults(faults);
+            let mut output = Vec::new();
+            multiplexer.read_to_end(&mut output).unwrap();
+            (output, multiplexer.injected_faults().to_vec())
+        };
+
+        let faults = FaultInjection::new()
+            .at_frame(FaultKind::UnknownStreamType, 1)
+            .at_frame(FaultKind::NonZeroPadding, 3)
+            .at_frame(FaultKind::TruncatedFinalFrame, 0);
+        let (output, injected) = generate(faults);
+        let kinds: Vec<_> = injected.iter().map(|fault| fault.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                FaultKind::UnknownStreamType,
+                FaultKind::NonZeroPadding,
+                FaultKind::TruncatedFinalFrame
+            ]
+        );
+
+        let mut decoder = DockerStreamDecoder::new().with_recovery(true);
+        let mut reported = Vec::new();
+        for result in decoder.decode(&output) {
+            match result {
+                Ok(chunk) if chunk.is_first() && chunk.stream_type != 1 => {
+                    reported.push((chunk.frame_index, chunk.frame_offset))
+                }
+                Ok(_) => {}
+                Err(DockerDecoderError::MalformedHeader {
+                    frame_index,
+                    offset,
+                    ..
+                }) => reported.push((frame_index, offset)),
+                Err(err) => panic!("unexpected error {}", err),
+            }
+        }
+        match decoder.finish() {
+            Err(DockerDecoderError::IncompleteBody {
+                frame_index,
+                offset,
+                ..
+            }) => reported.push((frame_index, offset)),
+            result => panic!("unexpected result {:?}", result),
+        }
+        let expected: Vec<_> = injected
+            .iter()
+            .map(|fault| (fault.frame_index, fault.offset))
+            .collect();
+        assert_eq!(reported, expected);
+
+        let (output, injected) = generate(FaultInjection::new().with_rate(FaultKind::BitFlip, 1.0));
+        assert_eq!(injected.len(), 4);
+        let (clean, _) = generate(FaultInjection::new());
+        let flipped: Vec<_> = (0..clean.len())
+            .filter(|&position| clean[position] != output[position])
+            .map(|position| position as u64)
+            .collect();
+        let offsets: Vec<_> = injected.iter().map(|fault| fault.offset).collect();
+        assert_eq!(flipped, offsets);
+    }
+
+    #[test]
+    fn streams_large_frames_without_buffering() {
+        const SIZE: u64 = 16 * 1024 * 1024;
+        let sources = vec![StreamSourceInfo {
+            stream_type: 1,
+            source: Box::new(io::repeat(b'x').take(SIZE + 10)),
+            size: Some(SIZE + 10),
+        }];
+        let mut mp = DockerStreamMultiplexer::new(sources, SIZE as u32, SIZE as u32);
+
+        let mut header = [0u8; FRAME_HEADER_LENGTH];
+        mp.read_exact(&mut header).unwrap();
+        assert_eq!(FrameHeader::parse(&header).unwrap().length as u64, SIZE);
+        let mut body = mp.by_ref().take(SIZE);
+        assert_eq!(io::copy(&mut body, &mut io::sink()).unwrap(), SIZE);
+        let mut rest = Vec::new();
+        mp.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, b"\x01\0\0\0\0\0\0\x0axxxxxxxxxx");
+        assert_eq!(mp.body_buffer.capacity(), 0);
+    }
+
+    #[test]
+    fn read_to_end() {
+        let (test_source, expected_output) = make_simple_input_output();
+        let mut mp = DockerStreamMultiplexer::new(test_source, 3, 3);
+
+        let mut output = Vec::<u8>::new();
+        mp.read_to_end(&mut output).unwrap();
+
+        assert_eq!(output, expected_output);
+    }
+}
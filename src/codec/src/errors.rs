@@ -0,0 +1,125 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::frame_header::FRAME_HEADER_LENGTH;
+
+/// Errors reported while decoding a multiplexed docker stream.
+///
+/// Frame related errors contain the frame ordinal number `frame_index` and
+/// the input `offset` of its header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DockerDecoderError {
+    /// Frame header contains a stream type, that isn't one of [`crate::StreamType`].
+    IncorrectFrameType {
+        stream_type: u8,
+        frame_index: u64,
+        offset: u64,
+    },
+    /// Frame header padding bytes aren't zeroed; contains the raw header bytes.
+    MalformedHeader {
+        header: [u8; FRAME_HEADER_LENGTH],
+        frame_index: u64,
+        offset: u64,
+    },
+    /// Frame length exceeds the decoder's maximum frame length.
+    FrameTooLarge {
+        length: u32,
+        frame_index: u64,
+        offset: u64,
+    },
+    /// Input ended in the middle of a frame header.
+    IncompleteHeader {
+        expected: usize,
+        actual: usize,
+        frame_index: u64,
+        offset: u64,
+    },
+    /// Input ended in the middle of a frame body.
+    IncompleteBody {
+        expected: u32,
+        actual: u32,
+        frame_index: u64,
+        offset: u64,
+    },
+    /// Input ended while the decoder was looking for a plausible header after
+    /// a malformed one; `skipped` bytes starting at the malformed header, at
+    /// `offset`, weren't decoded.
+    UnrecoveredData { skipped: u64, offset: u64 },
+}
+
+impl fmt::Display for DockerDecoderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MalformedHeader {
+                header,
+                frame_index,
+                offset,
+            } => {
+                write!(
+                    f,
+                    "Malformed docker frame header at offset {} (frame #{}), header contents dump: {:x?}",
+                    offset, frame_index, header
+                )
+            }
+            Self::IncorrectFrameType {
+                stream_type,
+                frame_index,
+                offset,
+            } => {
+                write!(
+                    f,
+                    "Incorrect DockerFrame type at offset {} (frame #{}): {}",
+                    offset, frame_index, stream_type
+                )
+            }
+            Self::FrameTooLarge {
+                length,
+                frame_index,
+                offset,
+            } => {
+                write!(
+                    f,
+                    "Docker frame at offset {} (frame #{}) is too large: {} bytes",
+                    offset, frame_index, length
+                )
+            }
+            Self::IncompleteHeader {
+                expected,
+                actual,
+                frame_index,
+                offset,
+            } => {
+                write!(
+                    f,
+                    "Input ended in the middle of a docker frame header at offset {} (frame #{}): expected {} bytes, got {}",
+                    offset, frame_index, expected, actual
+                )
+            }
+            Self::IncompleteBody {
+                expected,
+                actual,
+                frame_index,
+                offset,
+            } => {
+                write!(
+                    f,
+                    "Input ended in the middle of a docker frame body at offset {} (frame #{}): expected {} bytes, got {}",
+                    offset, frame_index, expected, actual
+                )
+            }
+            Self::UnrecoveredData { skipped, offset } => {
+                write!(
+                    f,
+                    "Input ended before a valid docker frame header was found: {} bytes skipped from offset {}",
+                    skipped, offset
+                )
+            }
+        }
+    }
+}
+
+impl Error for DockerDecoderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        None
+    }
+}
@@ -0,0 +1,131 @@
+use rand::{Rng, RngCore};
+use std::fmt;
+use std::str::FromStr;
+
+/// Kind of a fault, that [`crate::DockerStreamMultiplexer`] can inject.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FaultKind {
+    /// One of the header padding bytes is non-zero.
+    NonZeroPadding,
+    /// Header stream type isn't one of [`crate::StreamType`].
+    UnknownStreamType,
+    /// Header length is larger than the body.
+    OverstatedLength,
+    /// Header length is smaller than the body, at least one byte long.
+    UnderstatedLength,
+    /// The stream ends with a frame, which body is shorter than its header
+    /// states. It's an extra frame, added once all of the sources are exhausted.
+    TruncatedFinalFrame,
+    /// Frame header is written twice.
+    DuplicatedHeader,
+    /// A random bit of the frame header or body is flipped.
+    BitFlip,
+}
+
+impl FaultKind {
+    pub const ALL: [FaultKind; 7] = [
+        Self::NonZeroPadding,
+        Self::UnknownStreamType,
+        Self::OverstatedLength,
+        Self::UnderstatedLength,
+        Self::TruncatedFinalFrame,
+        Self::DuplicatedHeader,
+        Self::BitFlip,
+    ];
+}
+
+impl fmt::Display for FaultKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::NonZeroPadding => "padding",
+            Self::UnknownStreamType => "unknown-type",
+            Self::OverstatedLength => "overstated-length",
+            Self::UnderstatedLength => "understated-length",
+            Self::TruncatedFinalFrame => "truncated",
+            Self::DuplicatedHeader => "duplicated-header",
+            Self::BitFlip => "bit-flip",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for FaultKind {
+    type Err = ();
+
+    /// Parses a fault name, as printed by [`FaultKind`]'s `Display`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.to_string() == value)
+            .ok_or(())
+    }
+}
+
+/// A fault, that was injected into the stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectedFault {
+    pub kind: FaultKind,
+    /// Ordinal number of the generated frame, starting from 0.
+    pub frame_index: u64,
+    /// Output offset of the frame header, or of the flipped byte.
+    pub offset: u64,
+    /// Human readable details, e.g. the changed values.
+    pub detail: String,
+}
+
+/// Faults to inject into generated frames, either at random, with the given
+/// per-frame probability, or into frames with the given indices.
+///
+/// [`FaultKind::TruncatedFinalFrame`] is injected once at most: with its rate
+/// as probability, or always if it's given a frame index.
+#[derive(Clone, Debug, Default)]
+pub struct FaultInjection {
+    rates: Vec<(FaultKind, f64)>,
+    frames: Vec<(FaultKind, u64)>,
+}
+
+impl FaultInjection {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Injects `kind` faults into frames with `rate` probability, from 0 to 1.
+    pub fn with_rate(mut self, kind: FaultKind, rate: f64) -> Self {
+        self.rates.push((kind, rate.clamp(0.0, 1.0)));
+        self
+    }
+
+    /// Injects a `kind` fault into the frame with `frame_index`.
+    pub fn at_frame(mut self, kind: FaultKind, frame_index: u64) -> Self {
+        self.frames.push((kind, frame_index));
+        self
+    }
+
+    /// Picks faults of a frame, in the order of [`FaultKind::ALL`].
+    pub(crate) fn pick(&self, frame_index: u64, rng: &mut dyn RngCore) -> Vec<FaultKind> {
+        let mut picked = Vec::new();
+        for &(kind, rate) in &self.rates {
+            if kind != FaultKind::TruncatedFinalFrame && rng.gen_bool(rate) {
+                picked.push(kind);
+            }
+        }
+        for &(kind, index) in &self.frames {
+            if kind != FaultKind::TruncatedFinalFrame && index == frame_index {
+                picked.push(kind);
+            }
+        }
+        picked.sort_by_key(|kind| FaultKind::ALL.iter().position(|all| all == kind));
+        picked.dedup();
+        picked
+    }
+
+    /// Whether to add a truncated final frame.
+    pub(crate) fn truncate(&self, rng: &mut dyn RngCore) -> bool {
+        let kind = FaultKind::TruncatedFinalFrame;
+        self.frames.iter().any(|&(fault, _)| fault == kind)
+            || self
+                .rates
+                .iter()
+                .any(|&(fault, rate)| fault == kind && rng.gen_bool(rate))
+    }
+}
@@ -0,0 +1,150 @@
+use crate::errors::DockerDecoderError;
+use byteorder::{BigEndian, ByteOrder};
+use std::fmt;
+use std::str::FromStr;
+
+/// Length of a serialized [`FrameHeader`] in bytes.
+pub const FRAME_HEADER_LENGTH: usize = 8;
+
+/// IO stream a frame belongs to, as stored in the first byte of its header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StreamType {
+    Stdin = 0,
+    Stdout = 1,
+    Stderr = 2,
+}
+
+impl TryFrom<u8> for StreamType {
+    type Error = ();
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(StreamType::Stdin),
+            1 => Ok(StreamType::Stdout),
+            2 => Ok(StreamType::Stderr),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for StreamType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Stdin => "stdin",
+            Self::Stdout => "stdout",
+            Self::Stderr => "stderr",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for StreamType {
+    type Err = ();
+
+    /// Parses a stream name, as printed by [`StreamType`]'s `Display`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "stdin" => Ok(StreamType::Stdin),
+            "stdout" => Ok(StreamType::Stdout),
+            "stderr" => Ok(StreamType::Stderr),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<StreamType> for u8 {
+    fn from(value: StreamType) -> Self {
+        value as u8
+    }
+}
+
+/// Header preceding every frame of a multiplexed stream.
+///
+/// Layout is `[stream_type, 0, 0, 0, length (u32, big endian)]`. The stream
+/// type is kept as a raw byte, so unknown types survive a parse/serialize
+/// round trip; use [`StreamType::try_from`] to interpret it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameHeader {
+    pub stream_type: u8,
+    pub length: u32,
+}
+
+impl FrameHeader {
+    pub fn new(stream_type: u8, length: u32) -> Self {
+        Self {
+            stream_type,
+            length,
+        }
+    }
+
+    /// Parses a header, failing if any of the padding bytes is not zero.
+    ///
+    /// As a standalone header has no position, the returned error has zero
+    /// `frame_index` and `offset`.
+    pub fn parse(buffer: &[u8; FRAME_HEADER_LENGTH]) -> Result<Self, DockerDecoderError> {
+        if buffer[1] != 0u8 || buffer[2] != 0u8 || buffer[3] != 0u8 {
+            return Err(DockerDecoderError::MalformedHeader {
+                header: *buffer,
+                frame_index: 0,
+                offset: 0,
+            });
+        }
+        let length = BigEndian::read_u32(&buffer[4..]);
+        Ok(Self {
+            stream_type: buffer[0],
+            length,
+        })
+    }
+
+    /// Writes the header into the first [`FRAME_HEADER_LENGTH`] bytes of `buffer`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `buffer` is shorter than [`FRAME_HEADER_LENGTH`].
+    pub fn serialize(&self, buffer: &mut [u8]) {
+        assert!(
+            buffer.len() >= FRAME_HEADER_LENGTH,
+            "Buffer has enough space to write frame header in it"
+        );
+        buffer[0] = self.stream_type;
+        buffer[1..4].fill(0);
+        BigEndian::write_u32(&mut buffer[4..], self.length);
+    }
+}
+
+/// Serialized frame with the given body, for tests.
+#[cfg(test)]
+pub(crate) fn frame(stream_type: u8, body: &[u8]) -> Vec<u8> {
+    let mut frame = vec![0u8; FRAME_HEADER_LENGTH];
+    FrameHeader::new(stream_type, body.len() as u32).serialize(&mut frame);
+    frame.extend_from_slice(body);
+    frame
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn header_parse() {
+        let buffer: [u8; FRAME_HEADER_LENGTH] = [0x01, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56, 0x78];
+        let header = FrameHeader::parse(&buffer).unwrap();
+        assert_eq!(header.length, 0x12345678);
+        assert_eq!(header.stream_type, 1);
+    }
+
+    #[test]
+    fn mallformed_header_error() {
+        let buffer: [u8; FRAME_HEADER_LENGTH] = [0x00, 0x22, 0x00, 0x00, 0x11, 00, 0x00, 0x22];
+        let header = FrameHeader::parse(&buffer);
+        assert!(header.is_err());
+    }
+
+    #[test]
+    fn header_serialize() {
+        let header = FrameHeader::new(2, 0x12_34_56_78);
+        let mut buffer = [0xffu8; FRAME_HEADER_LENGTH];
+        header.serialize(&mut buffer);
+        assert_eq!(buffer, [0x02, 0x00, 0x00, 0x00, 0x12, 0x34, 0x56, 0x78])
+    }
+}
@@ -0,0 +1,61 @@
+//! Encoder and decoder for
+//! [multiplexed docker streams](https://docs.docker.com/engine/api/v1.43/#tag/Container/operation/ContainerAttach).
+//!
+//! A multiplexed stream is a sequence of frames, each one prefixed with an
+//! 8-byte [`FrameHeader`] holding the [`StreamType`] and the body length.
+//!
+//! - [`DockerStreamDecoder`] splits such a stream back into per-stream chunks;
+//! - [`DemuxReader`] reads a single stream from it, as a plain [`std::io::Read`];
+//! - [`DockerStreamDecoder::split`] splits it into stdout and stderr readers,
+//!   which can be consumed concurrently;
+//! - [`DockerStreamMultiplexer`] builds one from several sources, picked by a
+//!   [`SourceScheduler`];
+//! - [`MuxWriter`] writes one, framing writes of per-stream handles;
+//! - `ZeroCopyExtractor` extracts streams of one stored in a file, moving
+//!   frame bodies with `copy_file_range` and `splice` (Linux only).
+//!
+//! With the `tokio` feature enabled, `DockerStreamCodec` implements
+//! `tokio_util::codec::{Decoder, Encoder}`, to be used with `FramedRead` and
+//! `FramedWrite`.
+//!
+//! With the `timestamps` feature enabled, [`TimestampedLines`] handles the
+//! RFC3339 line prefixes of `docker logs --timestamps` output.
+
+mod demux_reader;
+mod docker_stream_decoder;
+mod docker_stream_multiplexer;
+mod errors;
+mod fault_injection;
+mod frame_header;
+mod mux_writer;
+mod source_scheduler;
+mod split_reader;
+#[cfg(all(test, target_os = "linux"))]
+mod temp_path;
+#[cfg(feature = "timestamps")]
+mod timestamps;
+#[cfg(feature = "tokio")]
+mod tokio_codec;
+#[cfg(target_os = "linux")]
+mod zero_copy;
+
+pub use demux_reader::DemuxReader;
+pub use docker_stream_decoder::{
+    DockerDecoderChunk, DockerStreamDecoder, DockerStreamDecoderChunks, Resynchronization,
+};
+pub use docker_stream_multiplexer::{DockerStreamMultiplexer, FramingMode, StreamSourceInfo};
+pub use errors::DockerDecoderError;
+pub use fault_injection::{FaultInjection, FaultKind, InjectedFault};
+pub use frame_header::{FrameHeader, StreamType, FRAME_HEADER_LENGTH};
+pub use mux_writer::{MuxStreamWriter, MuxWriter};
+pub use source_scheduler::{
+    ProportionalScheduler, RandomScheduler, RoundRobinScheduler, ScheduledSource,
+    SequentialScheduler, SourceScheduler, WeightedScheduler,
+};
+pub use split_reader::SplitReader;
+#[cfg(feature = "timestamps")]
+pub use timestamps::{TimestampFormat, TimestampOptions, TimestampedLines};
+#[cfg(feature = "tokio")]
+pub use tokio_codec::{DockerCodecError, DockerStreamCodec};
+#[cfg(target_os = "linux")]
+pub use zero_copy::{Extraction, ZeroCopyExtractor};
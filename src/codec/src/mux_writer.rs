@@ -0,0 +1,183 @@
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::frame_header::{FrameHeader, StreamType, FRAME_HEADER_LENGTH};
+
+/// Push-based multiplexer, writing frames of several streams into one writer.
+///
+/// Data is written through per-stream handles, each [`Write::write`] call on a
+/// handle producing a single frame. Wrap a handle into [`std::io::BufWriter`]
+/// or [`std::io::LineWriter`] to frame whole flushed buffers or lines instead.
+/// Handles can be moved to other threads, frames are never interleaved.
+///
+/// ```
+/// use std::io::Write;
+/// use docker_stream_codec::MuxWriter;
+///
+/// let mux = MuxWriter::new(Vec::new());
+/// mux.stdout().write_all(b"out").unwrap();
+/// mux.stderr().write_all(b"err").unwrap();
+/// assert_eq!(
+///     mux.into_inner().ok().unwrap(),
+///     [
+///         1, 0, 0, 0, 0, 0, 0, 3, b'o', b'u', b't',
+///         2, 0, 0, 0, 0, 0, 0, 3, b'e', b'r', b'r',
+///     ]
+/// );
+/// ```
+pub struct MuxWriter<W> {
+    writer: Arc<Mutex<W>>,
+}
+
+impl<W: Write> MuxWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(writer)),
+        }
+    }
+
+    /// Returns a handle writing frames of the specified stream.
+    pub fn stream(&self, stream_type: StreamType) -> MuxStreamWriter<W> {
+        MuxStreamWriter {
+            writer: self.writer.clone(),
+            stream_type,
+        }
+    }
+
+    pub fn stdin(&self) -> MuxStreamWriter<W> {
+        self.stream(StreamType::Stdin)
+    }
+
+    pub fn stdout(&self) -> MuxStreamWriter<W> {
+        self.stream(StreamType::Stdout)
+    }
+
+    pub fn stderr(&self) -> MuxStreamWriter<W> {
+        self.stream(StreamType::Stderr)
+    }
+
+    /// Unwraps the underlying writer, failing if any of the handles is alive.
+    pub fn into_inner(self) -> Result<W, Self> {
+        match Arc::try_unwrap(self.writer) {
+            Ok(writer) => Ok(writer
+                .into_inner()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())),
+            Err(writer) => Err(Self { writer }),
+        }
+    }
+}
+
+/// Handle returned by [`MuxWriter`], writing frames of a single stream.
+pub struct MuxStreamWriter<W> {
+    writer: Arc<Mutex<W>>,
+    stream_type: StreamType,
+}
+
+impl<W> MuxStreamWriter<W> {
+    pub fn stream_type(&self) -> StreamType {
+        self.stream_type
+    }
+
+    fn lock(&self) -> MutexGuard<'_, W> {
+        self.writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<W> Clone for MuxStreamWriter<W> {
+    fn clone(&self) -> Self {
+        Self {
+            writer: self.writer.clone(),
+            stream_type: self.stream_type,
+        }
+    }
+}
+
+impl<W: Write> Write for MuxStreamWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let length = std::cmp::min(buf.len(), u32::MAX as usize);
+        let mut header_buffer = [0u8; FRAME_HEADER_LENGTH];
+        FrameHeader::new(self.stream_type.into(), length as u32).serialize(&mut header_buffer);
+
+        let mut writer = self.lock();
+        writer.write_all(&header_buffer)?;
+        writer.write_all(&buf[..length])?;
+        Ok(length)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.lock().flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::DemuxReader;
+    use std::io::{BufWriter, Read};
+    use std::thread;
+
+    #[test]
+    fn frames_each_write() {
+        let mux = MuxWriter::new(Vec::new());
+        let mut stdout = mux.stdout();
+        stdout.write_all(b"one").unwrap();
+        mux.stdin().write_all(b"in").unwrap();
+        stdout.write_all(b"").unwrap();
+        stdout.write_all(b"two").unwrap();
+        drop(stdout);
+
+        let output = mux.into_inner().ok().unwrap();
+        assert_eq!(
+            output,
+            [
+                &[1, 0, 0, 0, 0, 0, 0, 3][..],
+                b"one",
+                &[0, 0, 0, 0, 0, 0, 0, 2],
+                b"in",
+                &[1, 0, 0, 0, 0, 0, 0, 3],
+                b"two",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn frames_buffered_data_on_flush() {
+        let mux = MuxWriter::new(Vec::new());
+        let mut stderr = BufWriter::new(mux.stderr());
+        stderr.write_all(b"a").unwrap();
+        stderr.write_all(b"b").unwrap();
+        stderr.flush().unwrap();
+        drop(stderr);
+
+        let output = mux.into_inner().ok().unwrap();
+        assert_eq!(output, [2, 0, 0, 0, 0, 0, 0, 2, b'a', b'b']);
+    }
+
+    #[test]
+    fn frames_arent_interleaved_between_threads() {
+        let mux = MuxWriter::new(Vec::new());
+        thread::scope(|scope| {
+            for stream in [mux.stdout(), mux.stderr()] {
+                scope.spawn(move || {
+                    let mut stream = stream;
+                    for _ in 0..1000 {
+                        stream.write_all(b"line\n").unwrap();
+                    }
+                });
+            }
+        });
+
+        let output = mux.into_inner().ok().unwrap();
+        let mut stdout = String::new();
+        DemuxReader::new(output.as_slice(), StreamType::Stdout)
+            .read_to_string(&mut stdout)
+            .unwrap();
+        assert_eq!(stdout, "line\n".repeat(1000));
+    }
+}
@@ -0,0 +1,179 @@
+use rand::{Rng, RngCore};
+
+/// Source, that isn't exhausted yet, as seen by a [`SourceScheduler`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ScheduledSource {
+    /// Position of the source in the list passed to the multiplexer.
+    pub index: usize,
+    pub stream_type: u8,
+    /// Number of bytes read from the source so far.
+    pub bytes_read: u64,
+}
+
+/// Strategy of picking a source for each next frame of a
+/// [`crate::DockerStreamMultiplexer`].
+pub trait SourceScheduler {
+    /// Returns the position in `sources` of the source to read the next frame
+    /// from. `sources` is never empty, exhausted sources are removed from it.
+    fn next_source(&mut self, sources: &[ScheduledSource], rng: &mut dyn RngCore) -> usize;
+}
+
+impl<S: SourceScheduler + ?Sized> SourceScheduler for Box<S> {
+    fn next_source(&mut self, sources: &[ScheduledSource], rng: &mut dyn RngCore) -> usize {
+        (**self).next_source(sources, rng)
+    }
+}
+
+/// Picks sources uniformly at random, the default.
+#[derive(Clone, Debug, Default)]
+pub struct RandomScheduler;
+
+impl SourceScheduler for RandomScheduler {
+    fn next_source(&mut self, sources: &[ScheduledSource], rng: &mut dyn RngCore) -> usize {
+        rng.gen_range(0..sources.len())
+    }
+}
+
+/// Picks sources in turn.
+#[derive(Clone, Debug, Default)]
+pub struct RoundRobinScheduler {
+    last_index: Option<usize>,
+}
+
+impl SourceScheduler for RoundRobinScheduler {
+    fn next_source(&mut self, sources: &[ScheduledSource], _rng: &mut dyn RngCore) -> usize {
+        let position = sources
+            .iter()
+            .position(|source| self.last_index.is_none_or(|last| source.index > last))
+            .unwrap_or(0);
+        self.last_index = Some(sources[position].index);
+        position
+    }
+}
+
+/// Drains sources one after another.
+#[derive(Clone, Debug, Default)]
+pub struct SequentialScheduler;
+
+impl SourceScheduler for SequentialScheduler {
+    fn next_source(&mut self, _sources: &[ScheduledSource], _rng: &mut dyn RngCore) -> usize {
+        0
+    }
+}
+
+/// Picks sources at random, in proportion to their weights.
+#[derive(Clone, Debug)]
+pub struct WeightedScheduler {
+    /// Indexed by source position in the multiplexer list, missing ones are 1
+    weights: Vec<u64>,
+}
+
+impl WeightedScheduler {
+    pub fn new(weights: Vec<u64>) -> Self {
+        Self { weights }
+    }
+}
+
+impl SourceScheduler for WeightedScheduler {
+    fn next_source(&mut self, sources: &[ScheduledSource], rng: &mut dyn RngCore) -> usize {
+        pick_weighted(sources, rng, |source| {
+            self.weights.get(source.index).copied().unwrap_or(1)
+        })
+    }
+}
+
+/// Picks sources at random, in proportion to the number of bytes left in
+/// them, so all of the sources tend to be exhausted at about the same time.
+#[derive(Clone, Debug)]
+pub struct ProportionalScheduler {
+    /// Indexed by source position in the multiplexer list
+    sizes: Vec<u64>,
+}
+
+impl ProportionalScheduler {
+    /// Creates a scheduler for sources of known `sizes`, e.g. file lengths.
+    pub fn new(sizes: Vec<u64>) -> Self {
+        Self { sizes }
+    }
+}
+
+impl SourceScheduler for ProportionalScheduler {
+    fn next_source(&mut self, sources: &[ScheduledSource], rng: &mut dyn RngCore) -> usize {
+        pick_weighted(sources, rng, |source| {
+            let size = self.sizes.get(source.index).copied().unwrap_or(0);
+            size.saturating_sub(source.bytes_read)
+        })
+    }
+}
+
+/// Weighted random pick, uniform if all of the weights are zero.
+fn pick_weighted(
+    sources: &[ScheduledSource],
+    rng: &mut dyn RngCore,
+    weight: impl Fn(&ScheduledSource) -> u64,
+) -> usize {
+    let total: u64 = sources.iter().map(&weight).sum();
+    if total == 0 {
+        return rng.gen_range(0..sources.len());
+    }
+    let mut target = rng.gen_range(0..total);
+    for (position, source) in sources.iter().enumerate() {
+        let weight = weight(source);
+        if target < weight {
+            return position;
+        }
+        target -= weight;
+    }
+    unreachable!("target is less than the total weight")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn sources(indices: &[usize]) -> Vec<ScheduledSource> {
+        indices
+            .iter()
+            .map(|&index| ScheduledSource {
+                index,
+                stream_type: index as u8,
+                bytes_read: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn round_robin_skips_removed_sources() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut scheduler = RoundRobinScheduler::default();
+        let all = sources(&[0, 1, 2]);
+        let picked: Vec<_> = (0..4)
+            .map(|_| scheduler.next_source(&all, &mut rng))
+            .collect();
+        assert_eq!(picked, [0, 1, 2, 0]);
+
+        let without_first = sources(&[1, 2]);
+        assert_eq!(scheduler.next_source(&without_first, &mut rng), 0);
+        assert_eq!(scheduler.next_source(&without_first, &mut rng), 1);
+    }
+
+    #[test]
+    fn weighted_picks_follow_weights() {
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        let mut scheduler = WeightedScheduler::new(vec![0, 3, 1]);
+        let all = sources(&[0, 1, 2]);
+        let mut counts = [0; 3];
+        for _ in 0..4000 {
+            counts[scheduler.next_source(&all, &mut rng)] += 1;
+        }
+        assert_eq!(counts[0], 0);
+        assert!((2800..3200).contains(&counts[1]), "{:?}", counts);
+
+        let mut scheduler = ProportionalScheduler::new(vec![100, 10]);
+        let mut all = sources(&[0, 1]);
+        all[0].bytes_read = 100;
+        assert_eq!(scheduler.next_source(&all, &mut rng), 1);
+    }
+}
@@ -0,0 +1,301 @@
+use std::collections::VecDeque;
+use std::io::{self, Read};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+use crate::docker_stream_decoder::DockerStreamDecoder;
+use crate::errors::DockerDecoderError;
+use crate::frame_header::StreamType;
+
+const BUFFER_SIZE: usize = 8192;
+
+const STDOUT_INDEX: usize = 0;
+const STDERR_INDEX: usize = 1;
+
+impl DockerStreamDecoder {
+    /// Splits a multiplexed stream into independent stdout and stderr readers.
+    ///
+    /// Both readers share `reader` and decode it on demand, so they can be
+    /// consumed concurrently from different threads. Data of the stream that
+    /// isn't being read right now is buffered, up to `capacity` bytes (plus one
+    /// read of the upstream) per stream; once the buffer is full, the other
+    /// reader blocks until it's drained. Consequently, reading only one of the
+    /// readers from a single thread blocks, unless the other one is dropped.
+    ///
+    /// Stdin frames are skipped. The first decoding or IO error ends both
+    /// streams: each reader returns it once, after all of the data decoded
+    /// before it.
+    ///
+    /// ```
+    /// use std::io::Read;
+    /// use docker_stream_codec::DockerStreamDecoder;
+    ///
+    /// let input: &[u8] = &[
+    ///     1, 0, 0, 0, 0, 0, 0, 3, b'o', b'u', b't',
+    ///     2, 0, 0, 0, 0, 0, 0, 3, b'e', b'r', b'r',
+    /// ];
+    /// let (mut stdout, mut stderr) = DockerStreamDecoder::new().split(input, 1024);
+    /// let stderr_thread = std::thread::spawn(move || {
+    ///     let mut buffer = String::new();
+    ///     stderr.read_to_string(&mut buffer).map(|_| buffer)
+    /// });
+    /// let mut buffer = String::new();
+    /// stdout.read_to_string(&mut buffer).unwrap();
+    /// assert_eq!(buffer, "out");
+    /// assert_eq!(stderr_thread.join().unwrap().unwrap(), "err");
+    /// ```
+    pub fn split<R: Read>(self, reader: R, capacity: usize) -> (SplitReader<R>, SplitReader<R>) {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                reader: Some(reader),
+                input: Some(vec![0u8; BUFFER_SIZE].into_boxed_slice()),
+                decoder: self,
+                queues: [Queue::new(), Queue::new()],
+                failure: None,
+                finished: false,
+            }),
+            ready: Condvar::new(),
+            capacity,
+        });
+        (
+            SplitReader {
+                shared: shared.clone(),
+                index: STDOUT_INDEX,
+            },
+            SplitReader {
+                shared,
+                index: STDERR_INDEX,
+            },
+        )
+    }
+}
+
+/// One of the readers returned by [`DockerStreamDecoder::split`].
+pub struct SplitReader<R> {
+    shared: Arc<Shared<R>>,
+    index: usize,
+}
+
+struct Shared<R> {
+    state: Mutex<State<R>>,
+    /// Notified whenever queues or upstream availability change.
+    ready: Condvar,
+    capacity: usize,
+}
+
+struct Queue {
+    data: VecDeque<u8>,
+    /// False, once the corresponding reader is dropped.
+    open: bool,
+    error_delivered: bool,
+}
+
+impl Queue {
+    fn new() -> Self {
+        Self {
+            data: VecDeque::new(),
+            open: true,
+            error_delivered: false,
+        }
+    }
+}
+
+enum Failure {
+    Io(io::ErrorKind, String),
+    Decoder(DockerDecoderError),
+}
+
+impl Failure {
+    fn to_io_error(&self) -> io::Error {
+        match self {
+            Self::Io(kind, message) => io::Error::new(*kind, message.clone()),
+            Self::Decoder(err) => io::Error::new(io::ErrorKind::InvalidData, err.clone()),
+        }
+    }
+}
+
+struct State<R> {
+    /// Upstream reader and its buffer; `None` while a reader thread reads it.
+    reader: Option<R>,
+    input: Option<Box<[u8]>>,
+    decoder: DockerStreamDecoder,
+    queues: [Queue; 2],
+    failure: Option<Failure>,
+    finished: bool,
+}
+
+impl<R> State<R> {
+    fn fail(&mut self, failure: Failure) {
+        self.finished = true;
+        self.failure = Some(failure);
+    }
+
+    fn decode(&mut self, input: &[u8]) {
+        for chunk_result in self.decoder.decode(input) {
+            match chunk_result {
+                Ok(chunk) => {
+                    let index = match StreamType::try_from(chunk.stream_type) {
+                        Ok(StreamType::Stdout) => STDOUT_INDEX,
+                        Ok(StreamType::Stderr) => STDERR_INDEX,
+                        _ => continue,
+                    };
+                    let queue = &mut self.queues[index];
+                    if queue.open {
+                        queue.data.extend(chunk.body);
+                    }
+                }
+                Err(err) => {
+                    self.finished = true;
+                    self.failure = Some(Failure::Decoder(err));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+impl<R> SplitReader<R> {
+    /// Stream, which data is returned by this reader.
+    pub fn stream_type(&self) -> StreamType {
+        match self.index {
+            STDOUT_INDEX => StreamType::Stdout,
+            _ => StreamType::Stderr,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<R>> {
+        self.shared
+            .state
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State<R>>) -> MutexGuard<'a, State<R>> {
+        self.shared
+            .ready
+            .wait(state)
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl<R: Read> Read for SplitReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.lock();
+        loop {
+            let queue = &mut state.queues[self.index];
+            if !queue.data.is_empty() {
+                let n_bytes = std::cmp::min(buf.len(), queue.data.len());
+                for (dest, byte) in buf.iter_mut().zip(queue.data.drain(..n_bytes)) {
+                    *dest = byte;
+                }
+                self.shared.ready.notify_all();
+                return Ok(n_bytes);
+            }
+            if state.finished {
+                let error_delivered = state.queues[self.index].error_delivered;
+                state.queues[self.index].error_delivered = true;
+                return match &state.failure {
+                    Some(failure) if !error_delivered => Err(failure.to_io_error()),
+                    _ => Ok(0),
+                };
+            }
+
+            let other = &state.queues[1 - self.index];
+            let other_is_full = other.open && other.data.len() >= self.shared.capacity;
+            if state.reader.is_none() || other_is_full {
+                state = self.wait(state);
+                continue;
+            }
+
+            let (Some(mut reader), Some(mut input)) = (state.reader.take(), state.input.take())
+            else {
+                unreachable!("Upstream reader and its buffer are taken together");
+            };
+            drop(state);
+            let result = reader.read(&mut input);
+            state = self.lock();
+            match result {
+                Ok(0) => {
+                    state.finished = true;
+                    if let Err(err) = state.decoder.finish() {
+                        state.fail(Failure::Decoder(err));
+                    }
+                }
+                Ok(n_bytes) => state.decode(&input[..n_bytes]),
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => state.fail(Failure::Io(err.kind(), err.to_string())),
+            }
+            state.reader = Some(reader);
+            state.input = Some(input);
+            self.shared.ready.notify_all();
+        }
+    }
+}
+
+impl<R> Drop for SplitReader<R> {
+    fn drop(&mut self) {
+        let mut state = self.lock();
+        let queue = &mut state.queues[self.index];
+        queue.open = false;
+        queue.data = VecDeque::new();
+        self.shared.ready.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::frame_header::frame;
+    use std::thread;
+
+    fn make_input(n_frames: usize) -> (Vec<u8>, Vec<u8>, Vec<u8>) {
+        let (mut input, mut stdout, mut stderr) = (Vec::new(), Vec::new(), Vec::new());
+        for i in 0..n_frames {
+            let (stream_type, expected) = match i % 3 {
+                0 | 1 => (1, &mut stdout),
+                _ => (2, &mut stderr),
+            };
+            let body = format!("frame {}\n", i).into_bytes();
+            input.extend(frame(stream_type, &body));
+            expected.extend_from_slice(&body);
+        }
+        (input, stdout, stderr)
+    }
+
+    #[test]
+    fn reads_streams_concurrently() {
+        let (input, expected_stdout, expected_stderr) = make_input(10_000);
+        let (mut stdout, mut stderr) = DockerStreamDecoder::new().split(input.as_slice(), 64);
+        thread::scope(|scope| {
+            let stderr_thread = scope.spawn(move || {
+                let mut output = Vec::new();
+                stderr.read_to_end(&mut output).unwrap();
+                output
+            });
+            let mut output = Vec::new();
+            stdout.read_to_end(&mut output).unwrap();
+            assert_eq!(output, expected_stdout);
+            assert_eq!(stderr_thread.join().unwrap(), expected_stderr);
+        });
+    }
+
+    #[test]
+    fn dropped_reader_doesnt_block_the_other_one() {
+        let (input, expected_stdout, _) = make_input(10_000);
+        let (mut stdout, stderr) = DockerStreamDecoder::new().split(input.as_slice(), 64);
+        drop(stderr);
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).unwrap();
+        assert_eq!(output, expected_stdout);
+    }
+
+    #[test]
+    fn reports_error_to_both_readers() {
+        let input: &[u8] = &[1, 0, 0, 0, 0, 0, 0, 2, b'o', b'k', 2, 0, 0];
+        let (mut stdout, mut stderr) = DockerStreamDecoder::new().split(input, 64);
+        let mut output = Vec::new();
+        assert!(stdout.read_to_end(&mut output).is_err());
+        assert_eq!(output, b"ok");
+        assert!(stderr.read_to_end(&mut output).is_err());
+        assert_eq!(stdout.read(&mut [0u8; 8]).unwrap(), 0);
+    }
+}
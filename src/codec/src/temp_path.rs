@@ -0,0 +1,13 @@
+//! Temporary file paths of tests, shared with the benchmarks through a `#[path]`
+//! module declaration.
+
+use std::path::PathBuf;
+
+/// Path of a temporary file unique to the current process.
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "docker-stream-codec-{}-{}",
+        std::process::id(),
+        name
+    ))
+}
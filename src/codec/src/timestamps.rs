@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat};
+
+use crate::docker_stream_decoder::DockerDecoderChunk;
+
+/// Longest RFC3339 timestamp, e.g. `2006-01-02T15:04:05.999999999+07:00`.
+const MAX_PREFIX_LENGTH: usize = 35;
+
+/// How [`TimestampedLines`] outputs timestamp prefixes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// Keep prefixes as they are.
+    #[default]
+    Original,
+    /// Remove prefixes.
+    Strip,
+    /// RFC3339 in the local timezone.
+    Local,
+    /// Seconds since the first line's timestamp, e.g. `+1.500000000`.
+    Relative,
+    /// Seconds since the Unix epoch, e.g. `1697630400.120000000`.
+    Epoch,
+}
+
+/// Options of [`TimestampedLines`].
+#[derive(Clone, Debug, Default)]
+pub struct TimestampOptions {
+    pub format: TimestampFormat,
+    /// Lines with an earlier timestamp are skipped.
+    pub since: Option<DateTime<FixedOffset>>,
+    /// Lines with a later timestamp are skipped.
+    pub until: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Default)]
+struct LineState {
+    /// True while the prefix of the current line is being collected.
+    in_prefix: bool,
+    prefix: Vec<u8>,
+    /// The current line is filtered out.
+    skipping: bool,
+}
+
+/// Timestamp-aware processing of `docker logs --timestamps` output.
+///
+/// With timestamps enabled, each line of frame bodies starts with an
+/// RFC3339Nano timestamp and a space. This layer parses these prefixes, even
+/// if they're split between several frames, reformatting them and filtering
+/// lines by time. Lines without a valid prefix are passed as is.
+///
+/// ```
+/// use docker_stream_codec::{DockerStreamDecoder, TimestampFormat, TimestampOptions, TimestampedLines};
+///
+/// let mut lines = TimestampedLines::new(TimestampOptions {
+///     format: TimestampFormat::Strip,
+///     ..Default::default()
+/// });
+/// let mut output = Vec::new();
+/// let mut decoder = DockerStreamDecoder::new();
+/// let input = b"\x01\0\0\0\0\0\0\x1b2023-10-18T12:00:00Z hello\n";
+/// for chunk in decoder.decode(input) {
+///     lines
+///         .process(&chunk.unwrap(), |_, bytes| {
+///             output.extend_from_slice(bytes);
+///             Ok::<(), ()>(())
+///         })
+///         .unwrap();
+/// }
+/// assert_eq!(output, b"hello\n");
+/// ```
+pub struct TimestampedLines {
+    options: TimestampOptions,
+    first_timestamp: Option<DateTime<FixedOffset>>,
+    /// Keyed by stream type, so [`TimestampedLines::finish`] outputs them in order.
+    streams: BTreeMap<u8, LineState>,
+}
+
+impl TimestampedLines {
+    pub fn new(options: TimestampOptions) -> Self {
+        Self {
+            options,
+            first_timestamp: None,
+            streams: BTreeMap::new(),
+        }
+    }
+
+    /// Processes a chunk, passing its stream type and the resulting bytes to
+    /// `emit`, possibly in several calls.
+    pub fn process<E>(
+        &mut self,
+        chunk: &DockerDecoderChunk,
+        mut emit: impl FnMut(u8, &[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let stream_type = chunk.stream_type;
+        let mut state = self.streams.remove(&stream_type).unwrap_or(LineState {
+            in_prefix: true,
+            ..Default::default()
+        });
+        let result = self.process_body(&mut state, chunk.body, &mut |bytes: &[u8]| {
+            emit(stream_type, bytes)
+        });
+        self.streams.insert(stream_type, state);
+        result
+    }
+
+    /// Outputs prefixes collected so far, call it at the end of input.
+    pub fn finish<E>(&mut self, mut emit: impl FnMut(u8, &[u8]) -> Result<(), E>) -> Result<(), E> {
+        for (stream_type, state) in std::mem::take(&mut self.streams) {
+            if state.in_prefix && !state.prefix.is_empty() {
+                emit(stream_type, &state.prefix)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn process_body<E>(
+        &mut self,
+        state: &mut LineState,
+        mut body: &[u8],
+        emit: &mut impl FnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        while !body.is_empty() {
+            if !state.in_prefix {
+                let line_end = match body.iter().position(|&c| c == b'\n') {
+                    Some(position) => {
+                        state.in_prefix = true;
+                        position + 1
+                    }
+                    None => body.len(),
+                };
+                if !state.skipping {
+                    emit(&body[..line_end])?;
+                }
+                body = &body[line_end..];
+                continue;
+            }
+
+            let space_left = MAX_PREFIX_LENGTH + 1 - state.prefix.len();
+            let prefix_end = body
+                .iter()
+                .take(space_left)
+                .position(|&c| c == b' ' || c == b'\n');
+            match prefix_end {
+                Some(position) if body[position] == b' ' => {
+                    state.prefix.extend_from_slice(&body[..position]);
+                    body = &body[position + 1..];
+                    self.output_prefix(state, emit)?;
+                }
+                Some(_) => self.pass_prefix(state, emit)?,
+                None if body.len() >= space_left => self.pass_prefix(state, emit)?,
+                None => {
+                    state.prefix.extend_from_slice(body);
+                    body = &[];
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Outputs collected prefix of a line, which turned out not to be a timestamp.
+    fn pass_prefix<E>(
+        &self,
+        state: &mut LineState,
+        emit: &mut impl FnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        state.in_prefix = false;
+        state.skipping = false;
+        emit(&state.prefix)?;
+        state.prefix.clear();
+        Ok(())
+    }
+
+    /// Outputs collected prefix, followed by a space in the original line.
+    fn output_prefix<E>(
+        &mut self,
+        state: &mut LineState,
+        emit: &mut impl FnMut(&[u8]) -> Result<(), E>,
+    ) -> Result<(), E> {
+        let timestamp = std::str::from_utf8(&state.prefix)
+            .ok()
+            .and_then(|prefix| DateTime::parse_from_rfc3339(prefix).ok());
+        let Some(timestamp) = timestamp else {
+            state.prefix.push(b' ');
+            return self.pass_prefix(state, emit);
+        };
+
+        state.in_prefix = false;
+        let first_timestamp = *self.first_timestamp.get_or_insert(timestamp);
+        state.skipping = self.options.since.is_some_and(|since| timestamp < since)
+            || self.options.until.is_some_and(|until| timestamp > until);
+        if !state.skipping {
+            let formatted = match self.options.format {
+                TimestampFormat::Original => None,
+                TimestampFormat::Strip => Some(String::new()),
+                TimestampFormat::Local => Some(format!(
+                    "{} ",
+                    timestamp
+                        .with_timezone(&Local)
+                        .to_rfc3339_opts(SecondsFormat::Nanos, false)
+                )),
+                TimestampFormat::Relative => {
+                    let nanos = (timestamp - first_timestamp)
+                        .num_nanoseconds()
+                        .unwrap_or(i64::MAX);
+                    let sign = if nanos < 0 { '-' } else { '+' };
+                    let nanos = nanos.unsigned_abs();
+                    Some(format!(
+                        "{}{}.{:09} ",
+                        sign,
+                        nanos / 1_000_000_000,
+                        nanos % 1_000_000_000
+                    ))
+                }
+                TimestampFormat::Epoch => Some(format!(
+                    "{}.{:09} ",
+                    timestamp.timestamp(),
+                    timestamp.timestamp_subsec_nanos()
+                )),
+            };
+            match formatted {
+                Some(formatted) => emit(formatted.as_bytes())?,
+                None => {
+                    state.prefix.push(b' ');
+                    emit(&state.prefix)?;
+                }
+            }
+        }
+        state.prefix.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::docker_stream_decoder::DockerStreamDecoder;
+    use crate::frame_header::frame;
+
+    fn process(options: TimestampOptions, input: &[u8]) -> String {
+        let mut lines = TimestampedLines::new(options);
+        let mut output = Vec::new();
+        let mut decoder = DockerStreamDecoder::new();
+        for chunk in decoder.decode(input) {
+            lines
+                .process(&chunk.unwrap(), |_, bytes| {
+                    output.extend_from_slice(bytes);
+                    Ok::<(), ()>(())
+                })
+                .unwrap();
+        }
+        lines
+            .finish(|_, bytes| {
+                output.extend_from_slice(bytes);
+                Ok::<(), ()>(())
+            })
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Lines with timestamps, split between frames in the middle of prefixes.
+    fn make_input() -> Vec<u8> {
+        let mut input = frame(1, b"2023-10-18T12:00:00.000000000Z first\n2023-10-18T12:");
+        input.extend(frame(1, b"00:01.500000000Z second\nno timestamp\n"));
+        input.extend(frame(1, b"2023-10-18T12:00:03.000000000Z third\n2023"));
+        input
+    }
+
+    #[test]
+    fn strips_timestamps_split_between_frames() {
+        let options = TimestampOptions {
+            format: TimestampFormat::Strip,
+            ..Default::default()
+        };
+        assert_eq!(
+            process(options, &make_input()),
+            "first\nsecond\nno timestamp\nthird\n2023"
+        );
+    }
+
+    #[test]
+    fn finish_outputs_pending_prefixes_by_stream_type() {
+        let mut input = frame(2, b"2023-10-18T12:00:00Z stderr\n2023");
+        input.extend(frame(1, b"2023-10-18T12:00:00Z stdout\n2024"));
+        assert_eq!(
+            process(TimestampOptions::default(), &input),
+            "2023-10-18T12:00:00Z stderr\n2023-10-18T12:00:00Z stdout\n20242023"
+        );
+    }
+
+    #[test]
+    fn reformats_timestamps() {
+        let options = TimestampOptions {
+            format: TimestampFormat::Relative,
+            ..Default::default()
+        };
+        assert_eq!(
+            process(options, &make_input()),
+            "+0.000000000 first\n+1.500000000 second\nno timestamp\n+3.000000000 third\n2023"
+        );
+        let options = TimestampOptions {
+            format: TimestampFormat::Epoch,
+            ..Default::default()
+        };
+        assert!(process(options, &make_input()).starts_with("1697630400.000000000 first\n"));
+    }
+
+    #[test]
+    fn filters_lines_by_time() {
+        let options = TimestampOptions {
+            format: TimestampFormat::Original,
+            since: DateTime::parse_from_rfc3339("2023-10-18T12:00:01Z").ok(),
+            until: DateTime::parse_from_rfc3339("2023-10-18T12:00:02Z").ok(),
+        };
+        assert_eq!(
+            process(options, &make_input()),
+            "2023-10-18T12:00:01.500000000Z second\nno timestamp\n2023"
+        );
+    }
+}
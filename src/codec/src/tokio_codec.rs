@@ -0,0 +1,200 @@
+use std::error::Error;
+use std::{fmt, io};
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::docker_stream_decoder::DockerStreamDecoder;
+use crate::errors::DockerDecoderError;
+use crate::frame_header::{FrameHeader, StreamType, FRAME_HEADER_LENGTH};
+
+/// [`tokio_util::codec`] implementation of the multiplexed docker stream.
+///
+/// Decodes whole frames as `(StreamType, Bytes)` pairs, e.g. with
+/// [`tokio_util::codec::FramedRead`], and encodes them back with
+/// [`tokio_util::codec::FramedWrite`].
+///
+/// Frame bodies are accumulated in memory until complete, so consider
+/// limiting frame length with [`DockerStreamDecoder::with_max_frame_length`]
+/// when reading untrusted input.
+pub struct DockerStreamCodec {
+    decoder: DockerStreamDecoder,
+    frame: BytesMut,
+}
+
+impl Default for DockerStreamCodec {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DockerStreamCodec {
+    pub fn new() -> Self {
+        Self::with_decoder(DockerStreamDecoder::new())
+    }
+
+    /// Creates a codec on top of a configured decoder.
+    pub fn with_decoder(decoder: DockerStreamDecoder) -> Self {
+        Self {
+            decoder,
+            frame: BytesMut::new(),
+        }
+    }
+}
+
+impl Decoder for DockerStreamCodec {
+    type Item = (StreamType, Bytes);
+    type Error = DockerCodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let offset_before = self.decoder.offset();
+        let mut result = Ok(None);
+        for chunk_result in self.decoder.decode(src) {
+            match chunk_result {
+                Ok(chunk) => {
+                    self.frame.extend_from_slice(chunk.body);
+                    if chunk.is_last() {
+                        let frame = self.frame.split().freeze();
+                        result = match StreamType::try_from(chunk.stream_type) {
+                            Ok(stream_type) => Ok(Some((stream_type, frame))),
+                            Err(_) => Err(DockerDecoderError::IncorrectFrameType {
+                                stream_type: chunk.stream_type,
+                                frame_index: chunk.frame_index,
+                                offset: chunk.frame_offset,
+                            }
+                            .into()),
+                        };
+                        break;
+                    }
+                }
+                Err(err) => {
+                    self.frame.clear();
+                    result = Err(err.into());
+                    break;
+                }
+            }
+        }
+        src.advance((self.decoder.offset() - offset_before) as usize);
+        result
+    }
+
+    fn decode_eof(&mut self, buf: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(buf)? {
+            Some(frame) => Ok(Some(frame)),
+            None => {
+                self.frame.clear();
+                self.decoder.finish()?;
+                Ok(None)
+            }
+        }
+    }
+}
+
+impl<B: AsRef<[u8]>> Encoder<(StreamType, B)> for DockerStreamCodec {
+    type Error = DockerCodecError;
+
+    fn encode(&mut self, item: (StreamType, B), dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let (stream_type, body) = item;
+        let body = body.as_ref();
+        let length = u32::try_from(body.len()).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Frame body doesn't fit into a docker frame",
+            )
+        })?;
+
+        let mut header_buffer = [0u8; FRAME_HEADER_LENGTH];
+        FrameHeader::new(stream_type.into(), length).serialize(&mut header_buffer);
+        dst.reserve(FRAME_HEADER_LENGTH + body.len());
+        dst.put_slice(&header_buffer);
+        dst.put_slice(body);
+        Ok(())
+    }
+}
+
+/// Errors of [`DockerStreamCodec`]
+#[derive(Debug)]
+pub enum DockerCodecError {
+    Io(io::Error),
+    Decoder(DockerDecoderError),
+}
+
+impl fmt::Display for DockerCodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "IO error: {}", err),
+            Self::Decoder(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for DockerCodecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Decoder(err) => Some(err),
+        }
+    }
+}
+
+impl From<io::Error> for DockerCodecError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<DockerDecoderError> for DockerCodecError {
+    fn from(value: DockerDecoderError) -> Self {
+        Self::Decoder(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn decodes_frames_fed_in_pieces() {
+        let mut codec = DockerStreamCodec::new();
+        let mut input = BytesMut::new();
+        codec
+            .encode((StreamType::Stdout, b"hello"), &mut input)
+            .unwrap();
+        codec.encode((StreamType::Stderr, b""), &mut input).unwrap();
+        codec
+            .encode((StreamType::Stdout, b"world"), &mut input)
+            .unwrap();
+
+        let mut src = BytesMut::new();
+        let mut frames = Vec::new();
+        for piece in input.chunks(3) {
+            src.extend_from_slice(piece);
+            while let Some(frame) = codec.decode(&mut src).unwrap() {
+                frames.push(frame);
+            }
+        }
+        assert!(codec.decode_eof(&mut src).unwrap().is_none());
+        assert_eq!(
+            frames,
+            vec![
+                (StreamType::Stdout, Bytes::from_static(b"hello")),
+                (StreamType::Stderr, Bytes::new()),
+                (StreamType::Stdout, Bytes::from_static(b"world")),
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_truncated_input_at_eof() {
+        let mut codec = DockerStreamCodec::new();
+        let mut src = BytesMut::from(&[1u8, 0, 0, 0, 0, 0, 0, 5, b'h'][..]);
+        assert!(codec.decode(&mut src).unwrap().is_none());
+        assert!(src.is_empty());
+        assert!(matches!(
+            codec.decode_eof(&mut src),
+            Err(DockerCodecError::Decoder(
+                DockerDecoderError::IncompleteBody { .. }
+            ))
+        ));
+    }
+}
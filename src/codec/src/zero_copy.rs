@@ -0,0 +1,319 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{FileExt, FileTypeExt};
+use std::ptr;
+
+use crate::frame_header::{FrameHeader, StreamType, FRAME_HEADER_LENGTH};
+
+/// Size of the input window headers and small bodies are read through.
+const WINDOW_SIZE: usize = 64 * 1024;
+
+/// Bodies of at least this size are moved by the kernel, smaller ones are
+/// copied through the window, as a syscall per frame costs more.
+const ZERO_COPY_MIN: usize = 16 * 1024;
+
+/// Largest number of bytes moved by a single syscall.
+const COPY_CHUNK_MAX: u64 = 1024 * 1024 * 1024;
+
+/// Result of [`ZeroCopyExtractor::extract`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Extraction {
+    /// Input offset the extraction stopped at: the end of input, or the first
+    /// frame, which can't be extracted as is.
+    pub offset: u64,
+    /// Number of frames extracted, i.e. the index of the frame at `offset`.
+    pub frames: u64,
+}
+
+struct Destination<'a> {
+    file: &'a File,
+    pipe: bool,
+    /// The kernel refused to move data into the destination, e.g. as it's on
+    /// another filesystem or opened for appending, so it's written instead.
+    fallback: bool,
+}
+
+/// Extracts streams of a multiplexed docker stream file, moving frame bodies
+/// straight into destination files or pipes with `copy_file_range` and
+/// `splice`, without copying them through userspace.
+///
+/// Only frame headers are parsed, so the extraction stops at the first frame
+/// with a malformed header, an unknown stream type, a length exceeding the
+/// maximum or a truncated body, leaving the rest of the input to
+/// [`crate::DockerStreamDecoder`] with its error reporting and recovery.
+/// Frames of streams without a destination are skipped.
+///
+/// ```no_run
+/// use std::fs::File;
+/// use docker_stream_codec::{StreamType, ZeroCopyExtractor};
+///
+/// let input = File::open("capture.vdm").unwrap();
+/// let output = File::create("stdout.txt").unwrap();
+/// let extraction = ZeroCopyExtractor::new(&input)
+///     .with_destination(StreamType::Stdout, &output)
+///     .extract()
+///     .unwrap()
+///     .expect("input is a regular file, output is a file or a pipe");
+/// assert_eq!(extraction.offset, input.metadata().unwrap().len());
+/// ```
+pub struct ZeroCopyExtractor<'a> {
+    input: &'a File,
+    /// Indexed by stream type
+    destinations: [Option<Destination<'a>>; 3],
+    max_frame_length: Option<u32>,
+    window: Vec<u8>,
+    /// Input offset of the window start
+    window_offset: u64,
+    /// Small bodies copied from the window, to be written into `pending_stream`
+    pending: Vec<u8>,
+    pending_stream: usize,
+}
+
+impl<'a> ZeroCopyExtractor<'a> {
+    pub fn new(input: &'a File) -> Self {
+        Self {
+            input,
+            destinations: [None, None, None],
+            max_frame_length: None,
+            window: Vec::new(),
+            window_offset: 0,
+            pending: Vec::new(),
+            pending_stream: 0,
+        }
+    }
+
+    /// Extracts frames of `stream_type` into `file`, at its current position.
+    pub fn with_destination(mut self, stream_type: StreamType, file: &'a File) -> Self {
+        self.destinations[stream_type as usize] = Some(Destination {
+            file,
+            pipe: false,
+            fallback: false,
+        });
+        self
+    }
+
+    /// Stops at frames with a larger body length, see
+    /// [`crate::DockerStreamDecoder::with_max_frame_length`].
+    pub fn with_max_frame_length(mut self, max_frame_length: Option<u32>) -> Self {
+        self.max_frame_length = max_frame_length;
+        self
+    }
+
+    /// Extracts frames from the start of input, until its end or the first
+    /// frame, which can't be extracted as is.
+    ///
+    /// Returns `None` without extracting anything, if the input isn't a
+    /// regular file or any of the destinations isn't a regular file or a pipe.
+    pub fn extract(mut self) -> io::Result<Option<Extraction>> {
+        let input_metadata = self.input.metadata()?;
+        if !input_metadata.is_file() {
+            return Ok(None);
+        }
+        for destination in self.destinations.iter_mut().flatten() {
+            let file_type = destination.file.metadata()?.file_type();
+            if !file_type.is_file() && !file_type.is_fifo() {
+                return Ok(None);
+            }
+            destination.pipe = file_type.is_fifo();
+        }
+
+        let input_length = input_metadata.len();
+        let mut extraction = Extraction {
+            offset: 0,
+            frames: 0,
+        };
+        while input_length - extraction.offset >= FRAME_HEADER_LENGTH as u64 {
+            let offset = extraction.offset;
+            let start = self.fill_window(offset, FRAME_HEADER_LENGTH)?;
+            let header = self.window[start..start + FRAME_HEADER_LENGTH]
+                .try_into()
+                .unwrap();
+            let Ok(header) = FrameHeader::parse(header) else {
+                break;
+            };
+            let Ok(stream_type) = StreamType::try_from(header.stream_type) else {
+                break;
+            };
+            let body_offset = offset + FRAME_HEADER_LENGTH as u64;
+            let body_length = header.length as usize;
+            if self
+                .max_frame_length
+                .is_some_and(|max_frame_length| header.length > max_frame_length)
+                || input_length - body_offset < body_length as u64
+            {
+                break;
+            }
+
+            let stream = stream_type as usize;
+            if self.destinations[stream].is_some() && body_length > 0 {
+                if stream != self.pending_stream || body_length >= ZERO_COPY_MIN {
+                    self.flush()?;
+                    self.pending_stream = stream;
+                }
+                if body_length < ZERO_COPY_MIN {
+                    let start = self.fill_window(body_offset, body_length)?;
+                    self.pending
+                        .extend_from_slice(&self.window[start..start + body_length]);
+                    if self.pending.len() >= WINDOW_SIZE {
+                        self.flush()?;
+                    }
+                } else {
+                    self.copy_body(stream, body_offset, body_length as u64)?;
+                }
+            }
+            extraction.offset = body_offset + body_length as u64;
+            extraction.frames += 1;
+        }
+        self.flush()?;
+        Ok(Some(extraction))
+    }
+
+    /// Reads the window from `offset`, unless it holds `length` input bytes
+    /// there already. Returns the window position of `offset`.
+    fn fill_window(&mut self, offset: u64, length: usize) -> io::Result<usize> {
+        let window_end = self.window_offset + self.window.len() as u64;
+        if offset < self.window_offset || offset + length as u64 > window_end {
+            self.window.resize(WINDOW_SIZE, 0);
+            let mut filled = 0;
+            while filled < length {
+                match self
+                    .input
+                    .read_at(&mut self.window[filled..], offset + filled as u64)
+                {
+                    Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                    Ok(n_bytes_read) => filled += n_bytes_read,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            self.window.truncate(filled);
+            self.window_offset = offset;
+        }
+        Ok((offset - self.window_offset) as usize)
+    }
+
+    /// Writes bodies copied from the window.
+    fn flush(&mut self) -> io::Result<()> {
+        if let Some(destination) = &self.destinations[self.pending_stream] {
+            let mut file = destination.file;
+            file.write_all(&self.pending)?;
+        }
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Moves a frame body into the destination of `stream`, writing whatever
+    /// the kernel refuses to move.
+    fn copy_body(&mut self, stream: usize, mut offset: u64, mut length: u64) -> io::Result<()> {
+        let Some(destination) = &mut self.destinations[stream] else {
+            return Ok(());
+        };
+        let mut file = destination.file;
+        while length > 0 && !destination.fallback {
+            let mut input_offset = offset as libc::loff_t;
+            let chunk = std::cmp::min(length, COPY_CHUNK_MAX) as usize;
+            // SAFETY: both descriptors stay open while the files are borrowed,
+            // and the input offset is a valid pointer.
+            let result = unsafe {
+                if destination.pipe {
+                    libc::splice(
+                        self.input.as_raw_fd(),
+                        &mut input_offset,
+                        file.as_raw_fd(),
+                        ptr::null_mut(),
+                        chunk,
+                        libc::SPLICE_F_MOVE,
+                    )
+                } else {
+                    libc::copy_file_range(
+                        self.input.as_raw_fd(),
+                        &mut input_offset,
+                        file.as_raw_fd(),
+                        ptr::null_mut(),
+                        chunk,
+                        0,
+                    )
+                }
+            };
+            match result {
+                -1 => {
+                    let err = io::Error::last_os_error();
+                    match err.raw_os_error() {
+                        Some(libc::EINTR) => {}
+                        Some(
+                            libc::ENOSYS
+                            | libc::EXDEV
+                            | libc::EINVAL
+                            | libc::EOPNOTSUPP
+                            | libc::EBADF
+                            | libc::EPERM,
+                        ) => destination.fallback = true,
+                        _ => return Err(err),
+                    }
+                }
+                0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+                n_bytes_copied => {
+                    offset += n_bytes_copied as u64;
+                    length -= n_bytes_copied as u64;
+                }
+            }
+        }
+
+        while length > 0 {
+            let chunk = std::cmp::min(length, WINDOW_SIZE as u64) as usize;
+            let start = self.fill_window(offset, chunk)?;
+            file.write_all(&self.window[start..start + chunk])?;
+            offset += chunk as u64;
+            length -= chunk as u64;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::frame_header::frame;
+    use crate::temp_path::temp_path;
+    use std::io::Read;
+
+    #[test]
+    fn extracts_frames_until_malformed_header() {
+        let large = vec![b'L'; ZERO_COPY_MIN * 3];
+        let mut capture = frame(1, b"small ");
+        capture.extend(frame(2, b"error"));
+        capture.extend(frame(1, &large));
+        capture.extend(frame(0, b""));
+        capture.extend(frame(1, b" end"));
+        let extracted_length = capture.len() as u64;
+        capture.extend([1, 0xff, 0, 0, 0, 0, 0, 1, b'x']);
+
+        let input_path = temp_path("input");
+        let output_path = temp_path("output");
+        std::fs::write(&input_path, &capture).unwrap();
+        let input = File::open(&input_path).unwrap();
+        let output = File::create(&output_path).unwrap();
+        let extraction = ZeroCopyExtractor::new(&input)
+            .with_destination(StreamType::Stdout, &output)
+            .extract()
+            .unwrap();
+        drop(output);
+
+        let mut extracted = Vec::new();
+        File::open(&output_path)
+            .unwrap()
+            .read_to_end(&mut extracted)
+            .unwrap();
+        std::fs::remove_file(input_path).unwrap();
+        std::fs::remove_file(output_path).unwrap();
+        assert_eq!(
+            extraction,
+            Some(Extraction {
+                offset: extracted_length,
+                frames: 5,
+            })
+        );
+        assert_eq!(extracted, [&b"small "[..], &large, b" end"].concat());
+    }
+}
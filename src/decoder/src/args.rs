@@ -1,4 +1,9 @@
-use clap::Parser;
+use chrono::{DateTime, FixedOffset};
+use clap::{error::ErrorKind, CommandFactory, Parser, ValueEnum};
+use docker_stream_codec::{StreamType, TimestampFormat};
+
+use crate::frame_list_writer::FrameSelection;
+use crate::json_file_writer::Clock;
 
 // @see https://docs.rs/clap/latest/clap/_derive/_tutorial/index.html
 
@@ -14,6 +19,7 @@ pub struct Args {
     pub stdin: Option<String>,
 
     /// Stdout stream destination filename. Defaults to stdout. Use '-' to set output to process stdout explicitely.
+    /// Used as the destination of all streams with non-raw output formats.
     #[arg(short = 'o', long, default_value = "-")]
     pub stdout: String,
 
@@ -21,21 +27,179 @@ pub struct Args {
     #[arg(short = 'e', long)]
     pub stderr: Option<String>,
 
+    /// Write all streams to the stdout destination in their original order,
+    /// prefixing each line with its stream
+    #[arg(short = 'T', long, conflicts_with_all = ["stdin", "stderr"])]
+    pub transcript: bool,
+
+    /// Transcript line prefix of a stream, e.g. 'stderr=E> '. Defaults to 'in| ', 'out| ' and 'err| '
+    #[arg(long = "prefix", value_name = "STREAM=PREFIX", value_parser = parse_prefix)]
+    pub prefixes: Vec<(StreamType, String)>,
+
+    /// Colour transcript lines by their stream
+    #[arg(long, value_enum, default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Output format
+    #[arg(long, value_enum, default_value_t = OutputFormat::Raw)]
+    pub format: OutputFormat,
+
+    /// Validate inputs, writing a JSON report of all violations to the stdout destination.
+    /// Exit code is a combination of violation bits: 4 malformed header, 8 unknown stream type,
+    /// 16 oversize frame, 32 truncated frame, 64 zero-length frame
+    #[arg(long, conflicts_with_all = ["format", "transcript", "frames", "fatal"])]
+    pub validate: bool,
+
+    /// Treat zero-length frames as violations while validating
+    #[arg(long, requires = "validate")]
+    pub reject_empty_frames: bool,
+
+    /// List frames themselves instead of their contents: offset, header bytes, stream type,
+    /// length and a payload preview
+    #[arg(long, conflicts_with_all = ["format", "transcript"])]
+    pub frames: bool,
+
+    /// Hexdump listed frames, selected by index ('3', '3-7') or by byte offset range ('@0x100-0x1ff')
+    #[arg(long, value_name = "SELECTION", value_parser = FrameSelection::parse, requires = "frames")]
+    pub hexdump: Vec<FrameSelection>,
+
+    /// Time of json-file entries without `docker logs --timestamps` prefix: 'now' or an RFC3339 timestamp
+    #[arg(long, value_parser = Clock::parse, default_value = "now")]
+    pub clock: Clock,
+
+    /// Handling of `docker logs --timestamps` line prefixes: original, strip, local, relative or epoch
+    #[arg(short = 't', long, value_parser = parse_timestamp_format)]
+    pub timestamps: Option<TimestampFormat>,
+
+    /// Skip lines with a timestamp prefix earlier than this RFC3339 timestamp
+    #[arg(long, value_parser = parse_time)]
+    pub since: Option<DateTime<FixedOffset>>,
+
+    /// Skip lines with a timestamp prefix later than this RFC3339 timestamp
+    #[arg(long, value_parser = parse_time)]
+    pub until: Option<DateTime<FixedOffset>>,
+
     /// Not try to recover from parsing errors and fail immediately
     #[arg(short = 'f', long, default_value_t = false)]
     pub fatal: bool,
 
+    /// Maximum frame length in bytes. Frames with a larger length in their header are
+    /// reported as errors and treated as malformed.
+    #[arg(short = 'l', long)]
+    pub max_frame_length: Option<u32>,
+
+    /// Copy frame bodies of input files through userspace buffers, rather than moving them
+    /// into destination files and pipes by the kernel (Linux only)
+    #[arg(long, default_value_t = false)]
+    pub no_zero_copy: bool,
+
     /// Silent -- do not print error information to stderr
     #[arg(short = 's', long, visible_alias = "silent", default_value_t = false)]
     pub silent: bool,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    /// Demultiplexed stream contents, each stream written to its destination
+    #[default]
+    Raw,
+    /// One JSON object per frame, with its stream, position and payload
+    Ndjson,
+    /// Stdout and stderr lines as entries of docker's json-file log driver
+    JsonFile,
+    /// Frame statistics report: counts, sizes, line counts and errors
+    Stats,
+    /// Frame statistics report as JSON
+    StatsJson,
+}
+
+impl OutputFormat {
+    /// Whether the format describes whole frames, rather than their contents.
+    pub fn is_frame_based(&self) -> bool {
+        matches!(self, Self::Ndjson | Self::Stats | Self::StatsJson)
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only if the destination is a terminal
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
 impl Args {
     pub fn parse() -> Args {
         let mut args = <Self as Parser>::parse();
-        if args.files.len() == 0 {
+        if args.files.is_empty() {
             args.files.push("-".into());
         }
+        if (args.format.is_frame_based() || args.frames || args.validate)
+            && args.handles_timestamps()
+        {
+            Self::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--timestamps, --since and --until can't be used with frame based formats",
+                )
+                .exit();
+        }
+        if args.format == OutputFormat::JsonFile
+            && matches!(
+                args.timestamps,
+                Some(TimestampFormat::Strip | TimestampFormat::Relative | TimestampFormat::Epoch)
+            )
+        {
+            Self::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--format json-file takes entry times from RFC3339 timestamp prefixes, --timestamps can only be original or local with it",
+                )
+                .exit();
+        }
+        if args.format != OutputFormat::Raw && args.transcript {
+            Self::command()
+                .error(
+                    ErrorKind::ArgumentConflict,
+                    "--transcript can only be used with the raw format",
+                )
+                .exit();
+        }
         args
     }
+
+    /// Whether line timestamp prefixes are reformatted or filtered.
+    pub fn handles_timestamps(&self) -> bool {
+        self.timestamps.is_some() || self.since.is_some() || self.until.is_some()
+    }
+}
+
+fn parse_timestamp_format(value: &str) -> Result<TimestampFormat, String> {
+    Ok(match value {
+        "original" => TimestampFormat::Original,
+        "strip" => TimestampFormat::Strip,
+        "local" => TimestampFormat::Local,
+        "relative" => TimestampFormat::Relative,
+        "epoch" => TimestampFormat::Epoch,
+        _ => return Err("expected one of original, strip, local, relative, epoch".into()),
+    })
+}
+
+fn parse_prefix(value: &str) -> Result<(StreamType, String), String> {
+    let (stream, prefix) = value
+        .split_once('=')
+        .ok_or("expected STREAM=PREFIX, e.g. 'stderr=E> '")?;
+    let stream_type = stream.parse::<StreamType>().map_err(|_| {
+        format!(
+            "unknown stream '{}', expected stdin, stdout or stderr",
+            stream
+        )
+    })?;
+    Ok((stream_type, prefix.to_string()))
+}
+
+fn parse_time(value: &str) -> Result<DateTime<FixedOffset>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map_err(|err| format!("expected an RFC3339 timestamp: {}", err))
 }
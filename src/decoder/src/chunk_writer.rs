@@ -1,57 +1,253 @@
-use crate::{args::Args, docker_stream_decoder::DockerDecoderChunk, frame_header::StreamType};
+use crate::{
+    args::{Args, ColorChoice},
+    decoder_output::{open_destination, DecoderOutput},
+};
+use docker_stream_codec::{DockerDecoderChunk, StreamType};
+#[cfg(target_os = "linux")]
+use docker_stream_codec::{Extraction, ZeroCopyExtractor};
 
-use std::{fs::File, io::BufWriter, io::Result, io::Write};
+use std::{
+    fs::File,
+    io::{BufWriter, IsTerminal, Result, Write},
+};
+
+const COLOR_RESET: &str = "\x1b[0m";
 
 pub struct DockerDecoderChunkWriter {
-    stdin: Option<BufWriter<File>>,
+    stdin: Option<BufWriter<Box<dyn Write>>>,
     stdout: BufWriter<Box<dyn Write>>,
     stderr: Option<BufWriter<Box<dyn Write>>>,
+    transcript: Option<Transcript>,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    zero_copy: ZeroCopy,
 }
 
 impl DockerDecoderChunkWriter {
     pub fn new(args: &Args) -> Result<Self> {
-        let stdout_file: Box<dyn Write> = match args.stdout.as_str() {
-            "-" => Box::new(std::io::stdout()),
-            _ => Box::new(File::create(&args.stdout)?),
+        let mut zero_copy = ZeroCopy {
+            enabled: cfg!(target_os = "linux") && !args.no_zero_copy && !args.transcript,
+            files: Default::default(),
+            max_frame_length: args.max_frame_length,
         };
-        let stdout_writer = BufWriter::new(stdout_file);
+        let stdout_writer = BufWriter::new(zero_copy.open(StreamType::Stdout, &args.stdout)?);
 
         let stdin_writer = match &args.stdin {
             None => None,
-            Some(filename) => Some(BufWriter::new(File::create(filename)?)),
+            Some(filename) => Some(BufWriter::new(zero_copy.open(StreamType::Stdin, filename)?)),
         };
-        let stderr_file: Option<Box<dyn Write>> = match args.stderr.as_deref() {
+        let stderr_writer = match &args.stderr {
             None => None,
-            Some("-") => Some(Box::new(std::io::stdout())),
-            Some(filename) => Some(Box::new(File::create(filename)?)),
+            Some(filename) => Some(BufWriter::new(
+                zero_copy.open(StreamType::Stderr, filename)?,
+            )),
         };
-        let stderr_writer = stderr_file.and_then(|file| Some(BufWriter::new(file)));
+
+        let transcript = args.transcript.then(|| {
+            let color = match args.color {
+                ColorChoice::Auto => args.stdout == "-" && std::io::stdout().is_terminal(),
+                ColorChoice::Always => true,
+                ColorChoice::Never => false,
+            };
+            let mut transcript = Transcript::new(color);
+            for (stream_type, prefix) in &args.prefixes {
+                transcript.prefixes[*stream_type as usize].clone_from(prefix);
+            }
+            transcript
+        });
 
         Ok(Self {
             stdin: stdin_writer,
             stdout: stdout_writer,
             stderr: stderr_writer,
+            transcript,
+            zero_copy,
         })
     }
 
-    pub fn write(&mut self, chunk: DockerDecoderChunk) -> Result<()> {
+    fn flush(&mut self) -> Result<()> {
+        if let Some(stdin) = &mut self.stdin {
+            stdin.flush()?;
+        }
+        self.stdout.flush()?;
+        if let Some(stderr) = &mut self.stderr {
+            stderr.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl DecoderOutput for DockerDecoderChunkWriter {
+    fn write(&mut self, chunk: &DockerDecoderChunk) -> Result<()> {
         if let Ok(stream_type) = StreamType::try_from(chunk.stream_type) {
+            if let Some(transcript) = &mut self.transcript {
+                return transcript.write(&mut self.stdout, stream_type, chunk.body);
+            }
             match stream_type {
                 StreamType::Stdin => {
                     if let Some(stdin) = &mut self.stdin {
-                        stdin.write(chunk.body)?;
+                        stdin.write_all(chunk.body)?;
                     }
                 }
                 StreamType::Stdout => {
-                    self.stdout.write(chunk.body)?;
+                    self.stdout.write_all(chunk.body)?;
                 }
                 StreamType::Stderr => {
                     if let Some(stderr) = &mut self.stderr {
-                        stderr.write(chunk.body)?;
+                        stderr.write_all(chunk.body)?;
                     }
                 }
             }
         }
         Ok(())
     }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(transcript) = &mut self.transcript {
+            transcript.finish(&mut self.stdout)?;
+        }
+        self.flush()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn extract(&mut self, input: &File) -> Result<Option<Extraction>> {
+        if !self.zero_copy.enabled {
+            return Ok(None);
+        }
+        self.flush()?;
+        let mut extractor =
+            ZeroCopyExtractor::new(input).with_max_frame_length(self.zero_copy.max_frame_length);
+        let stream_types = [StreamType::Stdin, StreamType::Stdout, StreamType::Stderr];
+        for (stream_type, file) in stream_types.into_iter().zip(&self.zero_copy.files) {
+            if let Some(file) = file {
+                extractor = extractor.with_destination(stream_type, file);
+            }
+        }
+        extractor.extract()
+    }
+}
+
+/// Destination files, which frame bodies can be moved into by the kernel.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+struct ZeroCopy {
+    enabled: bool,
+    /// Indexed by stream type
+    files: [Option<File>; 3],
+    max_frame_length: Option<u32>,
+}
+
+impl ZeroCopy {
+    /// Opens a destination, keeping a handle of its file if zero-copy is enabled.
+    /// Unlike others, the stdin destination is always a file.
+    fn open(&mut self, stream_type: StreamType, filename: &str) -> Result<Box<dyn Write>> {
+        let file = match filename {
+            #[cfg(target_os = "linux")]
+            "-" if self.enabled && stream_type != StreamType::Stdin => {
+                use std::os::fd::AsFd;
+                File::from(std::io::stdout().as_fd().try_clone_to_owned()?)
+            }
+            "-" if stream_type != StreamType::Stdin => return open_destination(filename),
+            _ => File::create(filename)?,
+        };
+        if self.enabled {
+            self.files[stream_type as usize] = Some(file.try_clone()?);
+        }
+        Ok(Box::new(file))
+    }
+}
+
+/// All of the streams in one destination, each line prefixed with its stream.
+///
+/// Lines are written once complete, so a line split between several frames
+/// isn't broken by lines of other streams, that came in between.
+struct Transcript {
+    /// Indexed by stream type
+    prefixes: [String; 3],
+    color: bool,
+    lines: [Vec<u8>; 3],
+}
+
+impl Transcript {
+    fn new(color: bool) -> Self {
+        Self {
+            prefixes: ["in| ".into(), "out| ".into(), "err| ".into()],
+            color,
+            lines: Default::default(),
+        }
+    }
+
+    fn write(
+        &mut self,
+        output: &mut impl Write,
+        stream_type: StreamType,
+        body: &[u8],
+    ) -> Result<()> {
+        let mut body = body;
+        while let Some(position) = body.iter().position(|&c| c == b'\n') {
+            let line = &mut self.lines[stream_type as usize];
+            line.extend_from_slice(&body[..position]);
+            body = &body[position + 1..];
+            self.write_line(output, stream_type)?;
+        }
+        self.lines[stream_type as usize].extend_from_slice(body);
+        Ok(())
+    }
+
+    /// Writes the lines, which didn't end till the end of input.
+    fn finish(&mut self, output: &mut impl Write) -> Result<()> {
+        for stream_type in [StreamType::Stdin, StreamType::Stdout, StreamType::Stderr] {
+            if !self.lines[stream_type as usize].is_empty() {
+                self.write_line(output, stream_type)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_line(&mut self, output: &mut impl Write, stream_type: StreamType) -> Result<()> {
+        let color = match stream_type {
+            StreamType::Stdin => "\x1b[36m",
+            StreamType::Stdout => "",
+            StreamType::Stderr => "\x1b[31m",
+        };
+        let colored = self.color && !color.is_empty();
+        let line = &mut self.lines[stream_type as usize];
+        if colored {
+            output.write_all(color.as_bytes())?;
+        }
+        output.write_all(self.prefixes[stream_type as usize].as_bytes())?;
+        output.write_all(line)?;
+        if colored {
+            output.write_all(COLOR_RESET.as_bytes())?;
+        }
+        output.write_all(b"\n")?;
+        line.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transcript_keeps_lines_split_between_frames() {
+        let mut transcript = Transcript::new(false);
+        let mut output = Vec::new();
+        transcript
+            .write(&mut output, StreamType::Stdout, b"hel")
+            .unwrap();
+        transcript
+            .write(&mut output, StreamType::Stderr, b"oops\n")
+            .unwrap();
+        transcript
+            .write(&mut output, StreamType::Stdout, b"lo\nwor")
+            .unwrap();
+        transcript
+            .write(&mut output, StreamType::Stdin, b"ls\n")
+            .unwrap();
+        transcript.finish(&mut output).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "err| oops\nout| hello\nin| ls\nout| wor\n"
+        );
+    }
 }
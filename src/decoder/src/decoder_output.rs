@@ -0,0 +1,87 @@
+use std::{fs::File, io::Result, io::Write};
+
+#[cfg(target_os = "linux")]
+use docker_stream_codec::Extraction;
+use docker_stream_codec::{DockerDecoderChunk, DockerDecoderError, TimestampOptions};
+
+use crate::args::{Args, OutputFormat};
+use crate::chunk_writer::DockerDecoderChunkWriter;
+use crate::frame_list_writer::FrameListWriter;
+use crate::json_file_writer::JsonFileWriter;
+use crate::ndjson_writer::NdjsonWriter;
+use crate::stats_writer::StatsWriter;
+use crate::timestamp_output::TimestampOutput;
+use crate::validator::Validator;
+
+/// Destination of decoded chunks, one per output format.
+pub trait DecoderOutput {
+    /// Called before decoding each of the inputs.
+    fn begin_input(&mut self, _filename: &str) {}
+
+    fn write(&mut self, chunk: &DockerDecoderChunk) -> Result<()>;
+
+    /// Extracts frames of an input file, moving their bodies into the destinations by the
+    /// kernel, if the output supports it. The rest of the input, starting at the returned
+    /// position, is decoded as usual.
+    #[cfg(target_os = "linux")]
+    fn extract(&mut self, _input: &File) -> Result<Option<Extraction>> {
+        Ok(None)
+    }
+
+    /// Called for each decoding error, including the ones recovered from.
+    fn error(&mut self, _err: &DockerDecoderError) -> Result<()> {
+        Ok(())
+    }
+
+    /// Flushes any pending data, called once all of the inputs are decoded.
+    fn finish(&mut self) -> Result<()>;
+
+    /// Process exit code, once finished.
+    fn exit_code(&self) -> i32 {
+        0
+    }
+}
+
+pub fn create(args: &Args) -> Result<Box<dyn DecoderOutput>> {
+    if args.validate {
+        return Ok(Box::new(Validator::new(
+            open_destination(&args.stdout)?,
+            args.reject_empty_frames,
+        )));
+    }
+    if args.frames {
+        return Ok(Box::new(FrameListWriter::new(
+            open_destination(&args.stdout)?,
+            args.hexdump.clone(),
+        )));
+    }
+    let output: Box<dyn DecoderOutput> = match args.format {
+        OutputFormat::Raw => Box::new(DockerDecoderChunkWriter::new(args)?),
+        OutputFormat::Ndjson => Box::new(NdjsonWriter::new(open_destination(&args.stdout)?)),
+        OutputFormat::JsonFile => Box::new(JsonFileWriter::new(
+            open_destination(&args.stdout)?,
+            args.clock,
+        )),
+        OutputFormat::Stats => Box::new(StatsWriter::new(open_destination(&args.stdout)?, false)),
+        OutputFormat::StatsJson => {
+            Box::new(StatsWriter::new(open_destination(&args.stdout)?, true))
+        }
+    };
+    if !args.handles_timestamps() {
+        return Ok(output);
+    }
+    let options = TimestampOptions {
+        format: args.timestamps.unwrap_or_default(),
+        since: args.since,
+        until: args.until,
+    };
+    Ok(Box::new(TimestampOutput::new(output, options)))
+}
+
+/// Opens an output file, '-' stands for the process stdout.
+pub fn open_destination(filename: &str) -> Result<Box<dyn Write>> {
+    Ok(match filename {
+        "-" => Box::new(std::io::stdout()),
+        _ => Box::new(File::create(filename)?),
+    })
+}
@@ -0,0 +1,227 @@
+use std::{
+    io::{BufWriter, Result, Write},
+    ops::RangeInclusive,
+};
+
+use docker_stream_codec::{
+    DockerDecoderChunk, DockerDecoderError, FrameHeader, StreamType, FRAME_HEADER_LENGTH,
+};
+
+use crate::decoder_output::DecoderOutput;
+
+/// Number of payload bytes shown in the listing.
+const PREVIEW_LENGTH: usize = 32;
+const HEXDUMP_LINE_LENGTH: usize = 16;
+
+/// Frames to hexdump, by their index or by a range of byte offsets they overlap.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FrameSelection {
+    Index(RangeInclusive<u64>),
+    Offset(RangeInclusive<u64>),
+}
+
+impl FrameSelection {
+    /// Parses frame indices, e.g. `3` or `3-7`, or byte offsets prefixed with
+    /// `@`, e.g. `@0x100-0x1ff`.
+    pub fn parse(value: &str) -> std::result::Result<Self, String> {
+        let (is_offset, range) = match value.strip_prefix('@') {
+            Some(range) => (true, range),
+            None => (false, value),
+        };
+        let (start, end) = range.split_once('-').unwrap_or((range, range));
+        let range = parse_number(start)?..=parse_number(end)?;
+        if range.is_empty() {
+            return Err(format!("empty range '{}'", value));
+        }
+        Ok(if is_offset {
+            Self::Offset(range)
+        } else {
+            Self::Index(range)
+        })
+    }
+
+    fn matches(&self, frame_index: u64, frame_offset: u64, frame_length: u32) -> bool {
+        match self {
+            Self::Index(range) => range.contains(&frame_index),
+            Self::Offset(range) => {
+                let frame_end = frame_offset + FRAME_HEADER_LENGTH as u64 + frame_length as u64;
+                frame_offset <= *range.end() && *range.start() < frame_end
+            }
+        }
+    }
+}
+
+fn parse_number(value: &str) -> std::result::Result<u64, String> {
+    let parsed = match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|err| format!("invalid number '{}': {}", value, err))
+}
+
+/// The frame currently being listed.
+struct Frame {
+    stream_type: u8,
+    index: u64,
+    offset: u64,
+    length: u32,
+    /// Beginning of the body, or all of it, if the frame is hexdumped
+    body: Vec<u8>,
+    hexdump: bool,
+}
+
+/// Lists frames themselves, rather than their contents, one row per frame.
+///
+/// Headers are reconstructed from the decoded frames, which is exact, as
+/// headers with non-zero padding bytes are reported as errors.
+pub struct FrameListWriter {
+    output: BufWriter<Box<dyn Write>>,
+    hexdump: Vec<FrameSelection>,
+    frame: Option<Frame>,
+}
+
+impl FrameListWriter {
+    pub fn new(output: Box<dyn Write>, hexdump: Vec<FrameSelection>) -> Self {
+        Self {
+            output: BufWriter::new(output),
+            hexdump,
+            frame: None,
+        }
+    }
+
+    fn write_frame(&mut self, frame: &Frame, is_complete: bool) -> Result<()> {
+        let mut header = [0u8; FRAME_HEADER_LENGTH];
+        FrameHeader::new(frame.stream_type, frame.length).serialize(&mut header);
+        let stream = match StreamType::try_from(frame.stream_type) {
+            Ok(stream_type) => stream_type.to_string(),
+            Err(_) => format!("unknown({})", frame.stream_type),
+        };
+        let preview_length = std::cmp::min(frame.body.len(), PREVIEW_LENGTH);
+        let mut preview: String = frame.body[..preview_length].escape_ascii().to_string();
+        if (frame.length as usize) > preview_length {
+            preview.push_str("...");
+        }
+        writeln!(
+            self.output,
+            "#{:<6} {:>10} {} {:<11} {:>10} \"{}\"{}",
+            frame.index,
+            frame.offset,
+            hex(&header),
+            stream,
+            frame.length,
+            preview,
+            if is_complete { "" } else { " (truncated)" },
+        )?;
+
+        if frame.hexdump {
+            let mut data = header.to_vec();
+            data.extend_from_slice(&frame.body);
+            for (line_index, line) in data.chunks(HEXDUMP_LINE_LENGTH).enumerate() {
+                let offset = frame.offset + (line_index * HEXDUMP_LINE_LENGTH) as u64;
+                let ascii: String = line
+                    .iter()
+                    .map(|&c| {
+                        if c.is_ascii_graphic() || c == b' ' {
+                            c as char
+                        } else {
+                            '.'
+                        }
+                    })
+                    .collect();
+                writeln!(
+                    self.output,
+                    "    {:08x}  {:<47}  |{}|",
+                    offset,
+                    hex(line),
+                    ascii
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl DecoderOutput for FrameListWriter {
+    fn write(&mut self, chunk: &DockerDecoderChunk) -> Result<()> {
+        if chunk.is_first() {
+            if let Some(frame) = self.frame.take() {
+                self.write_frame(&frame, false)?;
+            }
+            if let Some(resync) = chunk.resynchronized {
+                writeln!(self.output, "! {}", resync)?;
+            }
+            self.frame = Some(Frame {
+                stream_type: chunk.stream_type,
+                index: chunk.frame_index,
+                offset: chunk.frame_offset,
+                length: chunk.frame_length,
+                body: Vec::new(),
+                hexdump: self.hexdump.iter().any(|selection| {
+                    selection.matches(chunk.frame_index, chunk.frame_offset, chunk.frame_length)
+                }),
+            });
+        }
+        let Some(frame) = &mut self.frame else {
+            return Ok(());
+        };
+        let wanted = if frame.hexdump {
+            chunk.body.len()
+        } else {
+            PREVIEW_LENGTH.saturating_sub(frame.body.len())
+        };
+        frame
+            .body
+            .extend_from_slice(&chunk.body[..std::cmp::min(wanted, chunk.body.len())]);
+        if chunk.is_last() {
+            let frame = self.frame.take().unwrap();
+            self.write_frame(&frame, true)?;
+        }
+        Ok(())
+    }
+
+    fn error(&mut self, err: &DockerDecoderError) -> Result<()> {
+        if let Some(frame) = self.frame.take() {
+            self.write_frame(&frame, false)?;
+        }
+        writeln!(self.output, "! {}", err)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if let Some(frame) = self.frame.take() {
+            self.write_frame(&frame, false)?;
+        }
+        self.output.flush()
+    }
+}
+
+/// Space separated hex bytes.
+fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parses_frame_selections() {
+        assert_eq!(FrameSelection::parse("3"), Ok(FrameSelection::Index(3..=3)));
+        assert_eq!(
+            FrameSelection::parse("3-7"),
+            Ok(FrameSelection::Index(3..=7))
+        );
+        assert_eq!(
+            FrameSelection::parse("@0x100-0x1ff"),
+            Ok(FrameSelection::Offset(256..=511))
+        );
+        assert!(FrameSelection::parse("7-3").is_err());
+
+        let selection = FrameSelection::Offset(20..=20);
+        assert!(selection.matches(1, 10, 5));
+        assert!(!selection.matches(1, 10, 2));
+    }
+}
@@ -0,0 +1,238 @@
+use std::io::{BufWriter, Result, Write};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use docker_stream_codec::{DockerDecoderChunk, StreamType};
+use serde::Serialize;
+
+use crate::decoder_output::DecoderOutput;
+
+/// Docker splits longer lines into several partial entries.
+const MAX_LOG_LINE_LENGTH: usize = 16 * 1024;
+
+/// Source of entry timestamps for lines without a `--timestamps` prefix.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Clock {
+    /// Current system time
+    #[default]
+    Now,
+    Fixed(DateTime<Utc>),
+}
+
+impl Clock {
+    pub fn parse(value: &str) -> std::result::Result<Self, String> {
+        if value == "now" {
+            return Ok(Self::Now);
+        }
+        DateTime::parse_from_rfc3339(value)
+            .map(|time| Self::Fixed(time.with_timezone(&Utc)))
+            .map_err(|err| format!("expected 'now' or an RFC3339 timestamp: {}", err))
+    }
+
+    fn time(&self) -> DateTime<Utc> {
+        match self {
+            Self::Now => Utc::now(),
+            Self::Fixed(time) => *time,
+        }
+    }
+}
+
+#[derive(Default)]
+struct LineBuffer {
+    line: Vec<u8>,
+    /// Time of the previous entry, if it was a partial one.
+    partial_time: Option<DateTime<Utc>>,
+}
+
+/// Writes stdout and stderr as entries of docker's json-file log driver.
+pub struct JsonFileWriter {
+    output: BufWriter<Box<dyn Write>>,
+    clock: Clock,
+    stdout: LineBuffer,
+    stderr: LineBuffer,
+}
+
+#[derive(Serialize)]
+struct LogEntry<'a> {
+    log: &'a str,
+    stream: &'a str,
+    time: &'a str,
+}
+
+impl JsonFileWriter {
+    pub fn new(output: Box<dyn Write>, clock: Clock) -> Self {
+        Self {
+            output: BufWriter::new(output),
+            clock,
+            stdout: LineBuffer::default(),
+            stderr: LineBuffer::default(),
+        }
+    }
+
+    /// Writes the first `length` bytes of the buffered line as an entry,
+    /// keeping the rest for the next one.
+    fn write_entry(
+        output: &mut impl Write,
+        clock: &Clock,
+        stream_type: StreamType,
+        buffer: &mut LineBuffer,
+        length: usize,
+    ) -> Result<()> {
+        let line = String::from_utf8_lossy(&buffer.line[..length]);
+        let (time, log) = match buffer.partial_time {
+            Some(time) => (time, &line[..]),
+            None => split_timestamp(&line).unwrap_or((clock.time(), &line[..])),
+        };
+        let entry = LogEntry {
+            log,
+            stream: &stream_type.to_string(),
+            time: &format_time(&time),
+        };
+        serde_json::to_writer(&mut *output, &entry)?;
+        output.write_all(b"\n")?;
+
+        buffer.partial_time = if line.ends_with('\n') {
+            None
+        } else {
+            Some(time)
+        };
+        buffer.line.drain(..length);
+        Ok(())
+    }
+}
+
+impl DecoderOutput for JsonFileWriter {
+    fn write(&mut self, chunk: &DockerDecoderChunk) -> Result<()> {
+        let (stream_type, buffer) = match StreamType::try_from(chunk.stream_type) {
+            Ok(StreamType::Stdout) => (StreamType::Stdout, &mut self.stdout),
+            Ok(StreamType::Stderr) => (StreamType::Stderr, &mut self.stderr),
+            _ => return Ok(()),
+        };
+        let mut body = chunk.body;
+        while !body.is_empty() {
+            let space_left = MAX_LOG_LINE_LENGTH - buffer.line.len();
+            let line_end = match body.iter().position(|&c| c == b'\n') {
+                Some(position) if position < space_left => position + 1,
+                _ => std::cmp::min(space_left, body.len()),
+            };
+            buffer.line.extend_from_slice(&body[..line_end]);
+            body = &body[line_end..];
+            if buffer.line.ends_with(b"\n") {
+                let length = buffer.line.len();
+                Self::write_entry(&mut self.output, &self.clock, stream_type, buffer, length)?;
+            } else if buffer.line.len() >= MAX_LOG_LINE_LENGTH {
+                // A character split between entries would be replaced in both
+                let length = complete_utf8_length(&buffer.line);
+                Self::write_entry(&mut self.output, &self.clock, stream_type, buffer, length)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        for (stream_type, buffer) in [
+            (StreamType::Stdout, &mut self.stdout),
+            (StreamType::Stderr, &mut self.stderr),
+        ] {
+            if !buffer.line.is_empty() {
+                let length = buffer.line.len();
+                Self::write_entry(&mut self.output, &self.clock, stream_type, buffer, length)?;
+            }
+        }
+        self.output.flush()
+    }
+}
+
+/// Length of `bytes` without an incomplete UTF-8 sequence at the end.
+fn complete_utf8_length(bytes: &[u8]) -> usize {
+    let tail_start = bytes.len().saturating_sub(3);
+    for start in (tail_start..bytes.len()).rev() {
+        let sequence_length = match bytes[start] {
+            0x00..=0x7f => return bytes.len(),
+            // Continuation byte
+            0x80..=0xbf => continue,
+            0xc0..=0xdf => 2,
+            0xe0..=0xef => 3,
+            _ => 4,
+        };
+        return if bytes.len() - start < sequence_length {
+            start
+        } else {
+            bytes.len()
+        };
+    }
+    bytes.len()
+}
+
+/// Splits off `docker logs --timestamps` prefix of a line, if it has one.
+fn split_timestamp(line: &str) -> Option<(DateTime<Utc>, &str)> {
+    let (prefix, rest) = line.split_once(' ')?;
+    let time = DateTime::parse_from_rfc3339(prefix).ok()?;
+    Some((time.with_timezone(&Utc), rest))
+}
+
+/// Formats time the way Go's `time.RFC3339Nano` does, trimming trailing zeros.
+fn format_time(time: &DateTime<Utc>) -> String {
+    let formatted = time.to_rfc3339_opts(SecondsFormat::Nanos, true);
+    let formatted = formatted.trim_end_matches('Z');
+    let (seconds, fraction) = formatted.split_once('.').unwrap_or((formatted, ""));
+    let fraction = fraction.trim_end_matches('0');
+    if fraction.is_empty() {
+        format!("{}Z", seconds)
+    } else {
+        format!("{}.{}Z", seconds, fraction)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats_time_as_rfc3339_nano() {
+        let time = DateTime::parse_from_rfc3339("2023-10-18T12:00:00.120000000+02:00").unwrap();
+        assert_eq!(
+            format_time(&time.with_timezone(&Utc)),
+            "2023-10-18T10:00:00.12Z"
+        );
+        let time = DateTime::parse_from_rfc3339("2023-10-18T12:00:00Z").unwrap();
+        assert_eq!(
+            format_time(&time.with_timezone(&Utc)),
+            "2023-10-18T12:00:00Z"
+        );
+    }
+
+    #[test]
+    fn splits_long_lines_between_characters() {
+        let line = ["a".repeat(MAX_LOG_LINE_LENGTH - 1), "é\n".into()].concat();
+        let bytes = &line.as_bytes()[..MAX_LOG_LINE_LENGTH];
+        assert_eq!(complete_utf8_length(bytes), MAX_LOG_LINE_LENGTH - 1);
+        assert_eq!(complete_utf8_length(line.as_bytes()), line.len());
+        assert_eq!(complete_utf8_length(&"€".as_bytes()[..2]), 0);
+        assert_eq!(complete_utf8_length(b"ab\x80"), 3);
+
+        let mut buffer = LineBuffer {
+            line: bytes.to_vec(),
+            partial_time: None,
+        };
+        let mut output = Vec::new();
+        let length = complete_utf8_length(&buffer.line);
+        JsonFileWriter::write_entry(
+            &mut output,
+            &Clock::Now,
+            StreamType::Stdout,
+            &mut buffer,
+            length,
+        )
+        .unwrap();
+        assert_eq!(buffer.line, &"é".as_bytes()[..1]);
+        assert!(!String::from_utf8(output).unwrap().contains('\u{fffd}'));
+    }
+
+    #[test]
+    fn splits_timestamp_prefix() {
+        let (time, rest) = split_timestamp("2023-10-18T12:00:00.000000001Z line\n").unwrap();
+        assert_eq!(format_time(&time), "2023-10-18T12:00:00.000000001Z");
+        assert_eq!(rest, "line\n");
+        assert!(split_timestamp("plain line\n").is_none());
+    }
+}
@@ -1,8 +1,12 @@
 mod args;
 mod chunk_writer;
-mod docker_stream_decoder;
-mod errors;
-mod frame_header;
+mod decoder_output;
+mod frame_list_writer;
+mod json_file_writer;
+mod ndjson_writer;
+mod stats_writer;
+mod timestamp_output;
+mod validator;
 
 use std::error::Error;
 use std::{
@@ -11,21 +15,37 @@ use std::{
 };
 
 use args::Args;
-use errors::DockerDecoderError;
-use frame_header::StreamType;
+use docker_stream_codec::{DockerDecoderError, DockerStreamDecoder, StreamType};
 
 const BUFFER_SIZE: usize = 8192;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
     let mut buffer = [0u8; BUFFER_SIZE];
-    let mut chunk_writer = chunk_writer::DockerDecoderChunkWriter::new(&args)?;
+    let mut output = decoder_output::create(&args)?;
 
     for filename in &args.files {
-        let mut decoder = docker_stream_decoder::DockerStreamDecoder::new();
+        output.begin_input(filename);
+        let mut decoder = DockerStreamDecoder::new()
+            .with_recovery(!args.fatal)
+            .with_max_frame_length(args.max_frame_length);
         let file: Box<dyn Read> = match filename.as_str() {
             "-" => Box::new(std::io::stdin()),
-            _ => Box::new(File::open(filename)?),
+            _ => {
+                let file = File::open(filename)?;
+                #[cfg(target_os = "linux")]
+                let file = {
+                    use std::io::{Seek, SeekFrom};
+
+                    let mut file = file;
+                    if let Some(extraction) = output.extract(&file)? {
+                        file.seek(SeekFrom::Start(extraction.offset))?;
+                        decoder = decoder.with_position(extraction.offset, extraction.frames);
+                    }
+                    file
+                };
+                Box::new(file)
+            }
         };
         let mut reader = BufReader::new(file);
 
@@ -38,19 +58,28 @@ fn main() -> Result<(), Box<dyn Error>> {
             for chunk_result in decoder.decode(&buffer[0..bytes_read]) {
                 match chunk_result {
                     Ok(chunk) => {
-                        if let Err(_) = StreamType::try_from(chunk.stream_type) {
+                        if let Some(resync) = chunk.resynchronized {
+                            if !args.silent {
+                                eprintln!("{}", resync);
+                            }
+                        }
+                        if chunk.is_first() && StreamType::try_from(chunk.stream_type).is_err() {
+                            let err = DockerDecoderError::IncorrectFrameType {
+                                stream_type: chunk.stream_type,
+                                frame_index: chunk.frame_index,
+                                offset: chunk.frame_offset,
+                            };
                             if !args.silent {
-                                eprintln!("Incorrect docker stream type {}", chunk.stream_type);
+                                eprintln!("{}", err);
                             }
                             if args.fatal {
-                                return Err(Box::new(DockerDecoderError::IncorrectFrameType(
-                                    chunk.stream_type,
-                                )));
+                                return Err(Box::new(err));
                             }
                         }
-                        chunk_writer.write(chunk)?;
+                        output.write(&chunk)?;
                     }
                     Err(err) => {
+                        output.error(&err)?;
                         if !args.silent {
                             eprintln!("Error processing docker stream {}", err);
                         }
@@ -61,6 +90,24 @@ fn main() -> Result<(), Box<dyn Error>> {
                 }
             }
         }
+
+        if let Err(err) = decoder.finish() {
+            output.error(&err)?;
+            if args.fatal {
+                if !args.silent {
+                    eprintln!("Error processing docker stream {}: {}", filename, err);
+                }
+                return Err(Box::new(err));
+            }
+            if !args.silent {
+                eprintln!("Warning: {}: {}", filename, err);
+            }
+        }
+    }
+    output.finish()?;
+    let exit_code = output.exit_code();
+    if exit_code != 0 {
+        std::process::exit(exit_code);
     }
     Ok(())
 }
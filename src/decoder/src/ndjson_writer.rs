@@ -0,0 +1,76 @@
+use std::io::{BufWriter, Result, Write};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use docker_stream_codec::{DockerDecoderChunk, StreamType};
+use serde::Serialize;
+
+use crate::decoder_output::DecoderOutput;
+
+/// Writes one JSON object per frame, for consumption by `jq` and alike.
+pub struct NdjsonWriter {
+    output: BufWriter<Box<dyn Write>>,
+    body: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct FrameRecord<'a> {
+    stream: String,
+    stream_type: u8,
+    frame: u64,
+    offset: u64,
+    length: u32,
+    /// Either "utf-8" or "base64", if the payload isn't valid UTF-8
+    encoding: &'static str,
+    payload: &'a str,
+}
+
+impl NdjsonWriter {
+    pub fn new(output: Box<dyn Write>) -> Self {
+        Self {
+            output: BufWriter::new(output),
+            body: Vec::new(),
+        }
+    }
+}
+
+impl DecoderOutput for NdjsonWriter {
+    fn write(&mut self, chunk: &DockerDecoderChunk) -> Result<()> {
+        if chunk.is_first() {
+            // Dropping leftovers of a truncated frame from a previous input
+            self.body.clear();
+        }
+        self.body.extend_from_slice(chunk.body);
+        if !chunk.is_last() {
+            return Ok(());
+        }
+
+        let base64_payload;
+        let (encoding, payload) = match std::str::from_utf8(&self.body) {
+            Ok(payload) => ("utf-8", payload),
+            Err(_) => {
+                base64_payload = BASE64.encode(&self.body);
+                ("base64", base64_payload.as_str())
+            }
+        };
+        let record = FrameRecord {
+            stream: match StreamType::try_from(chunk.stream_type) {
+                Ok(stream_type) => stream_type.to_string(),
+                Err(_) => "unknown".into(),
+            },
+            stream_type: chunk.stream_type,
+            frame: chunk.frame_index,
+            offset: chunk.frame_offset,
+            length: chunk.frame_length,
+            encoding,
+            payload,
+        };
+        serde_json::to_writer(&mut self.output, &record)?;
+        self.output.write_all(b"\n")?;
+        self.body.clear();
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        self.output.flush()
+    }
+}
@@ -0,0 +1,407 @@
+use std::{
+    collections::BTreeMap,
+    io::{BufWriter, Result, Write},
+};
+
+use docker_stream_codec::{DockerDecoderChunk, DockerDecoderError, StreamType};
+use serde::Serialize;
+
+use crate::decoder_output::DecoderOutput;
+
+/// Width of the longest histogram bar in the text report.
+const HISTOGRAM_WIDTH: u64 = 40;
+
+/// Number of power of two sized frame length buckets: 0, 1, 2..=3, 4..=7 and
+/// so on, up to 2^31..=u32::MAX.
+const BUCKETS: usize = u32::BITS as usize + 1;
+
+/// Frame lengths, as stated in their headers, are only counted, so memory use
+/// doesn't depend on the number of frames.
+struct StreamStats {
+    frames: u64,
+    min: u32,
+    max: u32,
+    total: u64,
+    /// Frame counts by length bucket, see [`bucket_index`]
+    buckets: [u64; BUCKETS],
+    bytes: u64,
+    lines: u64,
+}
+
+impl Default for StreamStats {
+    fn default() -> Self {
+        Self {
+            frames: 0,
+            min: 0,
+            max: 0,
+            total: 0,
+            buckets: [0; BUCKETS],
+            bytes: 0,
+            lines: 0,
+        }
+    }
+}
+
+impl StreamStats {
+    fn add_frame(&mut self, length: u32) {
+        if self.frames == 0 {
+            self.min = length;
+            self.max = length;
+        } else {
+            self.min = self.min.min(length);
+            self.max = self.max.max(length);
+        }
+        self.frames += 1;
+        self.total += length as u64;
+        self.buckets[bucket_index(length)] += 1;
+    }
+}
+
+#[derive(Clone, Copy, Serialize)]
+struct FramePosition {
+    stream_type: u8,
+    frame: u64,
+    offset: u64,
+    length: u32,
+}
+
+/// Collects statistics of the decoded frames, writing a report once all of
+/// the inputs are decoded.
+pub struct StatsWriter {
+    output: BufWriter<Box<dyn Write>>,
+    json: bool,
+    streams: BTreeMap<u8, StreamStats>,
+    largest_frame: Option<FramePosition>,
+    errors: Vec<String>,
+    resyncs: u64,
+    skipped_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct Report {
+    frames: u64,
+    bytes: u64,
+    streams: Vec<StreamReport>,
+    largest_frame: Option<FramePosition>,
+    unknown_stream_types: Vec<u8>,
+    errors: Vec<String>,
+    /// Times decoding resumed after malformed data, which isn't an error by itself
+    resyncs: u64,
+    /// Malformed data skipped while resynchronizing
+    skipped_bytes: u64,
+}
+
+#[derive(Serialize)]
+struct StreamReport {
+    stream: String,
+    stream_type: u8,
+    frames: u64,
+    zero_length_frames: u64,
+    bytes: u64,
+    lines: u64,
+    min: u32,
+    max: u32,
+    mean: f64,
+    p50: u32,
+    p90: u32,
+    p99: u32,
+    histogram: Vec<HistogramBucket>,
+}
+
+/// Frames with lengths from `min` to `max` inclusive.
+#[derive(Serialize)]
+struct HistogramBucket {
+    min: u32,
+    max: u32,
+    frames: u64,
+}
+
+impl StatsWriter {
+    pub fn new(output: Box<dyn Write>, json: bool) -> Self {
+        Self {
+            output: BufWriter::new(output),
+            json,
+            streams: BTreeMap::new(),
+            largest_frame: None,
+            errors: Vec::new(),
+            resyncs: 0,
+            skipped_bytes: 0,
+        }
+    }
+
+    fn report(&self) -> Report {
+        let streams: Vec<StreamReport> = self
+            .streams
+            .iter()
+            .map(|(&stream_type, stats)| StreamReport::new(stream_type, stats))
+            .collect();
+        Report {
+            frames: streams.iter().map(|stream| stream.frames).sum(),
+            bytes: streams.iter().map(|stream| stream.bytes).sum(),
+            streams,
+            largest_frame: self.largest_frame,
+            unknown_stream_types: self
+                .streams
+                .keys()
+                .copied()
+                .filter(|&stream_type| StreamType::try_from(stream_type).is_err())
+                .collect(),
+            errors: self.errors.clone(),
+            resyncs: self.resyncs,
+            skipped_bytes: self.skipped_bytes,
+        }
+    }
+}
+
+impl DecoderOutput for StatsWriter {
+    fn write(&mut self, chunk: &DockerDecoderChunk) -> Result<()> {
+        if let Some(resync) = chunk.resynchronized {
+            self.resyncs += 1;
+            self.skipped_bytes += resync.skipped;
+        }
+        let stats = self.streams.entry(chunk.stream_type).or_default();
+        if chunk.is_first() {
+            stats.add_frame(chunk.frame_length);
+            if self
+                .largest_frame
+                .is_none_or(|largest| chunk.frame_length > largest.length)
+            {
+                self.largest_frame = Some(FramePosition {
+                    stream_type: chunk.stream_type,
+                    frame: chunk.frame_index,
+                    offset: chunk.frame_offset,
+                    length: chunk.frame_length,
+                });
+            }
+        }
+        stats.bytes += chunk.body.len() as u64;
+        stats.lines += chunk.body.iter().filter(|&&c| c == b'\n').count() as u64;
+        Ok(())
+    }
+
+    fn error(&mut self, err: &DockerDecoderError) -> Result<()> {
+        self.errors.push(err.to_string());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let report = self.report();
+        if self.json {
+            serde_json::to_writer_pretty(&mut self.output, &report)?;
+            self.output.write_all(b"\n")?;
+        } else {
+            report.write_text(&mut self.output)?;
+        }
+        self.output.flush()
+    }
+}
+
+impl StreamReport {
+    fn new(stream_type: u8, stats: &StreamStats) -> Self {
+        let frames = stats.frames;
+        Self {
+            stream: match StreamType::try_from(stream_type) {
+                Ok(stream_type) => stream_type.to_string(),
+                Err(_) => "unknown".into(),
+            },
+            stream_type,
+            frames,
+            zero_length_frames: stats.buckets[0],
+            bytes: stats.bytes,
+            lines: stats.lines,
+            min: stats.min,
+            max: stats.max,
+            mean: if frames > 0 {
+                stats.total as f64 / frames as f64
+            } else {
+                0.0
+            },
+            p50: percentile(stats, 50),
+            p90: percentile(stats, 90),
+            p99: percentile(stats, 99),
+            histogram: histogram(stats),
+        }
+    }
+}
+
+impl Report {
+    fn write_text(&self, output: &mut impl Write) -> Result<()> {
+        writeln!(output, "Frames: {}, bytes: {}", self.frames, self.bytes)?;
+        if let Some(frame) = &self.largest_frame {
+            writeln!(
+                output,
+                "Largest frame: #{} at offset {}, stream type {}, {} bytes",
+                frame.frame, frame.offset, frame.stream_type, frame.length
+            )?;
+        }
+        for stream in &self.streams {
+            writeln!(output)?;
+            writeln!(output, "{} ({}):", stream.stream, stream.stream_type)?;
+            writeln!(
+                output,
+                "  frames: {} ({} zero-length), bytes: {}, lines: {}",
+                stream.frames, stream.zero_length_frames, stream.bytes, stream.lines
+            )?;
+            writeln!(
+                output,
+                "  frame size: min {}, max {}, mean {:.1}, p50 {}, p90 {}, p99 {}",
+                stream.min, stream.max, stream.mean, stream.p50, stream.p90, stream.p99
+            )?;
+            let most_frames = stream
+                .histogram
+                .iter()
+                .map(|bucket| bucket.frames)
+                .max()
+                .unwrap_or(0);
+            let labels: Vec<String> = stream
+                .histogram
+                .iter()
+                .map(|bucket| format!("{}..={}", bucket.min, bucket.max))
+                .collect();
+            let label_width = labels.iter().map(String::len).max().unwrap_or(0);
+            for (label, bucket) in labels.iter().zip(&stream.histogram) {
+                let bar_length = (bucket.frames * HISTOGRAM_WIDTH).div_ceil(most_frames);
+                writeln!(
+                    output,
+                    "  {:>width$} {:>8} {}",
+                    label,
+                    bucket.frames,
+                    "#".repeat(bar_length as usize),
+                    width = label_width
+                )?;
+            }
+        }
+        if !self.unknown_stream_types.is_empty() {
+            writeln!(output)?;
+            writeln!(
+                output,
+                "Unknown stream types: {:?}",
+                self.unknown_stream_types
+            )?;
+        }
+        if self.resyncs > 0 {
+            writeln!(output)?;
+            writeln!(
+                output,
+                "Resynchronizations: {}, skipped bytes: {}",
+                self.resyncs, self.skipped_bytes
+            )?;
+        }
+        if !self.errors.is_empty() {
+            writeln!(output)?;
+            writeln!(output, "Errors: {}", self.errors.len())?;
+            for err in &self.errors {
+                writeln!(output, "  {}", err)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bucket of a frame length: the number of its significant bits.
+fn bucket_index(length: u32) -> usize {
+    (u32::BITS - length.leading_zeros()) as usize
+}
+
+/// Smallest and largest lengths of a bucket.
+fn bucket_bounds(index: usize) -> (u32, u32) {
+    match index {
+        0 => (0, 0),
+        _ => (1 << (index - 1), u32::MAX >> (u32::BITS as usize - index)),
+    }
+}
+
+/// Nearest-rank percentile, approximated by spreading the frames of its
+/// bucket evenly over the bucket's lengths, within the observed minimum and
+/// maximum.
+fn percentile(stats: &StreamStats, percent: u64) -> u32 {
+    if stats.frames == 0 {
+        return 0;
+    }
+    let rank = (stats.frames * percent).div_ceil(100).max(1);
+    let mut frames_before = 0;
+    for (index, &count) in stats.buckets.iter().enumerate() {
+        if frames_before + count >= rank {
+            let (min, max) = bucket_bounds(index);
+            let (min, max) = (min.max(stats.min) as u64, max.min(stats.max) as u64);
+            return (min + (max - min) * (rank - frames_before) / count) as u32;
+        }
+        frames_before += count;
+    }
+    stats.max
+}
+
+/// Frame counts of the buckets from the smallest to the largest length.
+fn histogram(stats: &StreamStats) -> Vec<HistogramBucket> {
+    if stats.frames == 0 {
+        return Vec::new();
+    }
+    (bucket_index(stats.min)..=bucket_index(stats.max))
+        .map(|index| {
+            let (min, max) = bucket_bounds(index);
+            HistogramBucket {
+                min,
+                max,
+                frames: stats.buckets[index],
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use docker_stream_codec::Resynchronization;
+
+    #[test]
+    fn computes_frame_size_statistics() {
+        let stats_of = |lengths: &[u32]| {
+            let mut stats = StreamStats::default();
+            lengths.iter().for_each(|&length| stats.add_frame(length));
+            stats
+        };
+        let stats = stats_of(&(1..=100).collect::<Vec<_>>());
+        assert_eq!((stats.min, stats.max, stats.total), (1, 100, 5050));
+        assert_eq!(percentile(&stats, 50), 50);
+        assert_eq!(percentile(&stats, 99), 99);
+        assert_eq!(percentile(&stats_of(&[7]), 90), 7);
+
+        let histogram = histogram(&stats_of(&[6, 0, 7, 1, 5]));
+        let buckets: Vec<_> = histogram
+            .iter()
+            .map(|bucket| (bucket.min, bucket.max, bucket.frames))
+            .collect();
+        assert_eq!(buckets, [(0, 0, 1), (1, 1, 1), (2, 3, 0), (4, 7, 3)]);
+    }
+
+    #[test]
+    fn counts_resyncs_separately_from_errors() {
+        let mut stats = StatsWriter::new(Box::new(std::io::sink()), false);
+        stats
+            .error(&DockerDecoderError::MalformedHeader {
+                header: [1, 0xff, 0, 0, 0, 0, 0, 1],
+                frame_index: 1,
+                offset: 10,
+            })
+            .unwrap();
+        stats
+            .write(&DockerDecoderChunk {
+                stream_type: 1,
+                body: b"hi",
+                frame_index: 1,
+                frame_offset: 21,
+                frame_length: 2,
+                offset_in_frame: 0,
+                resynchronized: Some(Resynchronization {
+                    skipped: 3,
+                    offset: 21,
+                }),
+            })
+            .unwrap();
+
+        let report = stats.report();
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!((report.resyncs, report.skipped_bytes), (1, 3));
+        assert_eq!(report.frames, 1);
+    }
+}
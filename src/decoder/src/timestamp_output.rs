@@ -0,0 +1,63 @@
+use std::io::Result;
+
+use docker_stream_codec::{
+    DockerDecoderChunk, DockerDecoderError, TimestampOptions, TimestampedLines,
+};
+
+use crate::decoder_output::DecoderOutput;
+
+/// Reformats and filters `docker logs --timestamps` line prefixes, passing
+/// the resulting chunks to another output.
+///
+/// Resulting chunks keep the position of the chunk they come from, while
+/// their bodies differ, so it's only meant for line based outputs.
+pub struct TimestampOutput {
+    inner: Box<dyn DecoderOutput>,
+    lines: TimestampedLines,
+}
+
+impl TimestampOutput {
+    pub fn new(inner: Box<dyn DecoderOutput>, options: TimestampOptions) -> Self {
+        Self {
+            inner,
+            lines: TimestampedLines::new(options),
+        }
+    }
+}
+
+impl DecoderOutput for TimestampOutput {
+    fn begin_input(&mut self, filename: &str) {
+        self.inner.begin_input(filename)
+    }
+
+    fn write(&mut self, chunk: &DockerDecoderChunk) -> Result<()> {
+        let inner = &mut self.inner;
+        self.lines.process(chunk, |_, body| {
+            inner.write(&DockerDecoderChunk { body, ..*chunk })
+        })
+    }
+
+    fn error(&mut self, err: &DockerDecoderError) -> Result<()> {
+        self.inner.error(err)
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let inner = &mut self.inner;
+        self.lines.finish(|stream_type, body| {
+            inner.write(&DockerDecoderChunk {
+                stream_type,
+                body,
+                frame_index: 0,
+                frame_offset: 0,
+                frame_length: 0,
+                offset_in_frame: 0,
+                resynchronized: None,
+            })
+        })?;
+        self.inner.finish()
+    }
+
+    fn exit_code(&self) -> i32 {
+        self.inner.exit_code()
+    }
+}
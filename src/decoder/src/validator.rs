@@ -0,0 +1,245 @@
+use std::io::{BufWriter, Result, Write};
+
+use docker_stream_codec::{DockerDecoderChunk, DockerDecoderError, StreamType};
+use serde::Serialize;
+
+use crate::decoder_output::DecoderOutput;
+
+/// Kinds of violations, each one sets its own bit of the process exit code.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViolationKind {
+    /// Header with non-zero padding bytes, or undecodable data following it
+    MalformedHeader,
+    UnknownStreamType,
+    /// Frame length exceeds `--max-frame-length`
+    OversizeFrame,
+    /// Input ended in the middle of a frame
+    TruncatedFrame,
+    /// Zero-length frame, only with `--reject-empty-frames`
+    EmptyFrame,
+}
+
+impl ViolationKind {
+    /// Exit code bit, starting from 4 as 1 and 2 stand for IO and usage errors.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::MalformedHeader => 4,
+            Self::UnknownStreamType => 8,
+            Self::OversizeFrame => 16,
+            Self::TruncatedFrame => 32,
+            Self::EmptyFrame => 64,
+        }
+    }
+
+    fn of(err: &DockerDecoderError) -> Self {
+        match err {
+            DockerDecoderError::MalformedHeader { .. }
+            | DockerDecoderError::UnrecoveredData { .. } => Self::MalformedHeader,
+            DockerDecoderError::IncorrectFrameType { .. } => Self::UnknownStreamType,
+            DockerDecoderError::FrameTooLarge { .. } => Self::OversizeFrame,
+            DockerDecoderError::IncompleteHeader { .. }
+            | DockerDecoderError::IncompleteBody { .. } => Self::TruncatedFrame,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Violation {
+    kind: ViolationKind,
+    /// Frame index, unless the violation is about undecodable data
+    frame: Option<u64>,
+    offset: u64,
+    message: String,
+}
+
+#[derive(Serialize)]
+struct InputReport {
+    file: String,
+    /// Frame headers read, including rejected ones
+    frames: u64,
+    bytes: u64,
+    violations: Vec<Violation>,
+}
+
+#[derive(Serialize)]
+struct Report<'a> {
+    valid: bool,
+    exit_code: i32,
+    inputs: &'a [InputReport],
+}
+
+/// Collects every violation of the inputs, writing a JSON report once all of
+/// them are decoded.
+pub struct Validator {
+    output: BufWriter<Box<dyn Write>>,
+    reject_empty_frames: bool,
+    inputs: Vec<InputReport>,
+}
+
+impl Validator {
+    pub fn new(output: Box<dyn Write>, reject_empty_frames: bool) -> Self {
+        Self {
+            output: BufWriter::new(output),
+            reject_empty_frames,
+            inputs: Vec::new(),
+        }
+    }
+
+    fn input(&mut self) -> &mut InputReport {
+        if self.inputs.is_empty() {
+            self.begin_input("-");
+        }
+        self.inputs.last_mut().unwrap()
+    }
+}
+
+impl DecoderOutput for Validator {
+    fn begin_input(&mut self, filename: &str) {
+        self.inputs.push(InputReport {
+            file: filename.to_string(),
+            frames: 0,
+            bytes: 0,
+            violations: Vec::new(),
+        });
+    }
+
+    fn write(&mut self, chunk: &DockerDecoderChunk) -> Result<()> {
+        let reject_empty_frames = self.reject_empty_frames;
+        let input = self.input();
+        input.bytes += chunk.body.len() as u64;
+        if !chunk.is_first() {
+            return Ok(());
+        }
+        input.frames = input.frames.max(chunk.frame_index + 1);
+
+        if StreamType::try_from(chunk.stream_type).is_err() {
+            let err = DockerDecoderError::IncorrectFrameType {
+                stream_type: chunk.stream_type,
+                frame_index: chunk.frame_index,
+                offset: chunk.frame_offset,
+            };
+            input.violations.push(Violation {
+                kind: ViolationKind::UnknownStreamType,
+                frame: Some(chunk.frame_index),
+                offset: chunk.frame_offset,
+                message: err.to_string(),
+            });
+        }
+        if reject_empty_frames && chunk.frame_length == 0 {
+            input.violations.push(Violation {
+                kind: ViolationKind::EmptyFrame,
+                frame: Some(chunk.frame_index),
+                offset: chunk.frame_offset,
+                message: format!(
+                    "Zero-length docker frame at offset {} (frame #{})",
+                    chunk.frame_offset, chunk.frame_index
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    fn error(&mut self, err: &DockerDecoderError) -> Result<()> {
+        let kind = ViolationKind::of(err);
+        let (frame, offset) = match *err {
+            DockerDecoderError::MalformedHeader {
+                frame_index,
+                offset,
+                ..
+            }
+            | DockerDecoderError::IncorrectFrameType {
+                frame_index,
+                offset,
+                ..
+            }
+            | DockerDecoderError::FrameTooLarge {
+                frame_index,
+                offset,
+                ..
+            }
+            | DockerDecoderError::IncompleteHeader {
+                frame_index,
+                offset,
+                ..
+            }
+            | DockerDecoderError::IncompleteBody {
+                frame_index,
+                offset,
+                ..
+            } => (Some(frame_index), offset),
+            DockerDecoderError::UnrecoveredData { offset, .. } => (None, offset),
+        };
+        let input = self.input();
+        if let DockerDecoderError::MalformedHeader { frame_index, .. }
+        | DockerDecoderError::FrameTooLarge { frame_index, .. }
+        | DockerDecoderError::IncompleteBody { frame_index, .. } = *err
+        {
+            input.frames = input.frames.max(frame_index + 1);
+        }
+        input.violations.push(Violation {
+            kind,
+            frame,
+            offset,
+            message: err.to_string(),
+        });
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        let exit_code = self.exit_code();
+        let report = Report {
+            valid: exit_code == 0,
+            exit_code,
+            inputs: &self.inputs,
+        };
+        serde_json::to_writer_pretty(&mut self.output, &report)?;
+        self.output.write_all(b"\n")?;
+        self.output.flush()
+    }
+
+    fn exit_code(&self) -> i32 {
+        self.inputs
+            .iter()
+            .flat_map(|input| &input.violations)
+            .fold(0, |code, violation| code | violation.kind.exit_code())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chunk(stream_type: u8, frame_index: u64, body: &[u8]) -> DockerDecoderChunk<'_> {
+        DockerDecoderChunk {
+            stream_type,
+            body,
+            frame_index,
+            frame_offset: frame_index * 8,
+            frame_length: body.len() as u32,
+            offset_in_frame: 0,
+            resynchronized: None,
+        }
+    }
+
+    #[test]
+    fn combines_violations_into_exit_code() {
+        let mut validator = Validator::new(Box::new(std::io::sink()), true);
+        validator.begin_input("capture.vdm");
+        validator.write(&chunk(1, 0, b"fine")).unwrap();
+        assert_eq!(validator.exit_code(), 0);
+
+        validator.write(&chunk(7, 1, b"")).unwrap();
+        validator
+            .error(&DockerDecoderError::IncompleteBody {
+                expected: 10,
+                actual: 2,
+                frame_index: 2,
+                offset: 23,
+            })
+            .unwrap();
+        assert_eq!(validator.inputs[0].violations.len(), 3);
+        assert_eq!(validator.inputs[0].frames, 3);
+        assert_eq!(validator.exit_code(), 8 | 64 | 32);
+    }
+}
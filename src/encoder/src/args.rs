@@ -1,8 +1,7 @@
 use std::{error::Error, fmt};
 
-use clap::Parser;
-
-const FRAME_SIZE_ABS_MAX: u32 = 4096;
+use clap::{Parser, ValueEnum};
+use docker_stream_codec::{DockerStreamMultiplexer, FaultKind, StreamType};
 
 // @see https://docs.rs/clap/latest/clap/_derive/_tutorial/index.html
 
@@ -26,33 +25,138 @@ pub struct Args {
     #[arg(short = 'e', long)]
     pub stderr: Option<String>,
 
-    /// Frame size max
+    /// Docker json-file log (`<id>-json.log`) to replay instead of stream source files, one
+    /// frame per entry. Can be specified multiple times, files are replayed in order.
+    #[arg(short = 'j', long, conflicts_with_all = ["stdin", "stdout", "stderr"])]
+    pub json_file: Vec<String>,
+
+    /// Frame specification file (TOML with a .toml extension, JSON otherwise) to write
+    /// exactly the listed frames, instead of reading stream source files
+    #[arg(short = 's', long, conflicts_with_all = ["stdin", "stdout", "stderr", "json_file"])]
+    pub spec: Option<String>,
+
+    /// Command to run, capturing its stdout and stderr instead of reading stream source files
+    #[arg(last = true, conflicts_with_all = ["stdin", "stdout", "stderr", "json_file", "spec"])]
+    pub command: Vec<String>,
+
+    /// Pass stdin to the command, writing it as stdin frames too
+    #[arg(long, requires = "command")]
+    pub forward_stdin: bool,
+
+    /// Prefix each line replayed from a json-file log with its timestamp, like `docker logs --timestamps`
+    #[arg(short = 't', long, requires = "json_file", default_value_t = false)]
+    pub timestamps: bool,
+
+    /// Frame size max, up to 4294967295. Frames of sources other than regular files (pipes,
+    /// devices) and line-aligned frames are buffered, so they are limited to 1 MiB (1048576)
     #[arg(short = 'M', long, default_value_t = 200)]
     pub frame_max: u32,
 
     /// Frame size min. Can be specified as negative value (offset from frame_max) or 0 -- equals to frame_size_max
     #[arg(short = 'm', long, default_value_t = 100)]
-    pub frame_min: i32,
+    pub frame_min: i64,
+
+    /// How source data is split into frames
+    #[arg(long, value_enum, default_value_t = Framing::Bytes)]
+    pub framing: Framing,
+
+    /// Number of lines per frame with the lines framing
+    #[arg(short = 'n', long, default_value_t = 1)]
+    pub lines: u32,
+
+    /// Strategy of picking the source of each frame
+    #[arg(long, value_enum, default_value_t = Schedule::Random)]
+    pub schedule: Schedule,
+
+    /// Weight of a stream with the weighted schedule, e.g. 'stdout=3'. Defaults to 1.
+    #[arg(short = 'w', long = "weight", value_name = "STREAM=RATIO", value_parser = parse_weight)]
+    pub weights: Vec<(StreamType, u64)>,
+
+    /// Inject faults: 'KIND=RATE' into frames with RATE probability, 'KIND@INDEX' into the frame
+    /// with INDEX. Kinds are padding, unknown-type, overstated-length, understated-length,
+    /// truncated (final frame), duplicated-header and bit-flip
+    #[arg(short = 'F', long = "fault", value_name = "FAULT", value_parser = parse_fault)]
+    pub faults: Vec<FaultSpec>,
+
+    /// File to write injected faults to, one JSON object per line
+    #[arg(long, requires = "faults")]
+    pub fault_log: Option<String>,
+
+    /// Seed of frame sizes and source selection, the same seed and inputs produce the same
+    /// stream. A random one is used and printed to stderr if not specified.
+    #[arg(long)]
+    pub seed: Option<u64>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Framing {
+    /// Frames of frame_min..=frame_max bytes, cut at arbitrary bytes
+    #[default]
+    Bytes,
+    /// --lines lines per frame, lines longer than frame_max are split
+    Lines,
+    /// Frames of up to frame_min..=frame_max bytes, cut after the last newline
+    LineBreak,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Schedule {
+    /// Uniformly at random
+    #[default]
+    Random,
+    /// Sources in turn
+    RoundRobin,
+    /// Drain sources one after another: stdin, stdout, stderr
+    Sequential,
+    /// At random, in proportion to --weight ratios
+    Weighted,
+    /// At random, in proportion to the bytes left in each source, so they finish together
+    Proportional,
 }
 
 impl Args {
     pub fn parse() -> Result<Args, ArgsError> {
         let mut args = <Self as Parser>::parse();
-        if args.stdin.is_none() && args.stdout.is_none() && args.stderr.is_none() {
+        if args.stdin.is_none()
+            && args.stdout.is_none()
+            && args.stderr.is_none()
+            && args.json_file.is_empty()
+            && args.spec.is_none()
+            && args.command.is_empty()
+        {
             return Err(ArgsError::NoInputSpecified);
         }
-        if args.frame_max > FRAME_SIZE_ABS_MAX {
-            return Err(ArgsError::FrameSizeExceeded(args.frame_max));
+        if !args.weights.is_empty() && args.schedule != Schedule::Weighted {
+            return Err(ArgsError::WeightWithoutWeightedSchedule);
+        }
+        if args.framing != Framing::Bytes {
+            args.check_buffered_frame_max()?;
+        }
+        if args.lines == 0 {
+            return Err(ArgsError::ZeroLinesPerFrame);
         }
         if args.frame_min <= 0 {
-            args.frame_min = (args.frame_max as i32 + args.frame_min) as i32
-        } else if args.frame_min > args.frame_max as i32 {
-            args.frame_min = args.frame_max as i32;
+            let frame_min = args.frame_min + args.frame_max as i64;
+            // An empty frame would be taken for the end of its source
+            if u32::try_from(frame_min).map_or(true, |frame_min| frame_min == 0) {
+                return Err(ArgsError::FrameSizeMinTooSmall(args.frame_min));
+            }
+            args.frame_min = frame_min;
+        } else if args.frame_min > args.frame_max as i64 {
+            args.frame_min = args.frame_max as i64;
         }
         Ok(args)
     }
 
-    pub fn get_sources(&self) -> SourcesIterator {
+    /// Fails if frame size max exceeds the size of buffered frames.
+    pub fn check_buffered_frame_max(&self) -> Result<(), ArgsError> {
+        if self.frame_max > DockerStreamMultiplexer::BUFFERED_FRAME_MAX {
+            return Err(ArgsError::BufferedFrameSizeExceeded(self.frame_max));
+        }
+        Ok(())
+    }
+
+    pub fn get_sources(&self) -> SourcesIterator<'_> {
         SourcesIterator {
             args: self,
             last_checked: 0,
@@ -60,6 +164,51 @@ impl Args {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FaultSpec {
+    Rate(FaultKind, f64),
+    Frame(FaultKind, u64),
+}
+
+fn parse_fault(value: &str) -> Result<FaultSpec, String> {
+    let parse_kind = |kind: &str| {
+        kind.parse::<FaultKind>()
+            .map_err(|_| format!("unknown fault '{}'", kind))
+    };
+    if let Some((kind, rate)) = value.split_once('=') {
+        let rate: f64 = rate
+            .parse()
+            .map_err(|err| format!("invalid rate '{}': {}", rate, err))?;
+        if !(0.0..=1.0).contains(&rate) {
+            return Err(format!("rate {} is out of 0..1 range", rate));
+        }
+        return Ok(FaultSpec::Rate(parse_kind(kind)?, rate));
+    }
+    if let Some((kind, index)) = value.split_once('@') {
+        let index = index
+            .parse()
+            .map_err(|err| format!("invalid frame index '{}': {}", index, err))?;
+        return Ok(FaultSpec::Frame(parse_kind(kind)?, index));
+    }
+    Err("expected KIND=RATE or KIND@INDEX, e.g. 'bit-flip=0.01' or 'padding@3'".into())
+}
+
+fn parse_weight(value: &str) -> Result<(StreamType, u64), String> {
+    let (stream, weight) = value
+        .split_once('=')
+        .ok_or("expected STREAM=RATIO, e.g. 'stdout=3'")?;
+    let stream_type = stream.parse::<StreamType>().map_err(|_| {
+        format!(
+            "unknown stream '{}', expected stdin, stdout or stderr",
+            stream
+        )
+    })?;
+    let weight = weight
+        .parse()
+        .map_err(|err| format!("invalid ratio '{}': {}", weight, err))?;
+    Ok((stream_type, weight))
+}
+
 pub struct StreamFilename<'a> {
     pub stream_type: u8,
     pub filename: &'a String,
@@ -90,30 +239,46 @@ impl<'a> Iterator for SourcesIterator<'a> {
                 });
             }
         }
-        return None;
+        None
     }
 }
 
 #[derive(Debug)]
 pub enum ArgsError {
     NoInputSpecified,
-    FrameSizeExceeded(u32),
+    BufferedFrameSizeExceeded(u32),
+    FrameSizeMinTooSmall(i64),
+    WeightWithoutWeightedSchedule,
+    ZeroLinesPerFrame,
 }
 
 impl fmt::Display for ArgsError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::NoInputSpecified => {
-                write!(f, "No input files were specified, you must specify any of --stdin, --stdout or --stderr files")
+                write!(f, "No input files were specified, you must specify any of --stdin, --stdout, --stderr, --json-file or --spec files, or a command after --")
             }
-            Self::FrameSizeExceeded(header) => {
+            Self::BufferedFrameSizeExceeded(frame_max) => {
                 write!(
                     f,
-                    "Specified frame size exceeds maximum possible value: provided {}, max possible is {}",
-                    header,
-                    FRAME_SIZE_ABS_MAX
+                    "Frame size max {} exceeds {} bytes, the limit of line-aligned frames and frames of sources other than regular files",
+                    frame_max,
+                    DockerStreamMultiplexer::BUFFERED_FRAME_MAX
                 )
             }
+            Self::FrameSizeMinTooSmall(frame_min) => {
+                write!(
+                    f,
+                    "Frame size min {} must be greater than minus frame size max, frames are at least 1 byte long",
+                    frame_min
+                )
+            }
+            Self::WeightWithoutWeightedSchedule => {
+                write!(f, "--weight can only be used with --schedule weighted")
+            }
+            Self::ZeroLinesPerFrame => {
+                write!(f, "Number of lines per frame must be at least 1")
+            }
         }
     }
 }
@@ -0,0 +1,225 @@
+use std::{
+    io::{self, Read, Write},
+    process::{ChildStdin, Command, ExitStatus, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use docker_stream_codec::{MuxStreamWriter, MuxWriter};
+
+const BUFFER_SIZE: usize = 8192;
+
+/// How often stdin forwarding checks if the command has exited.
+#[cfg(unix)]
+const STDIN_POLL_TIMEOUT_MS: libc::c_int = 100;
+
+/// Runs a command, writing each read of its stdout and stderr as a frame, in
+/// the order of arrival. With `forward_stdin`, our stdin is passed to the
+/// command and written as stdin frames as well.
+pub fn capture<W: Write + Send + 'static>(
+    command: &[String],
+    forward_stdin: bool,
+    mux: &MuxWriter<W>,
+) -> io::Result<ExitStatus> {
+    let (program, args) = command
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "no command specified"))?;
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(if forward_stdin {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| io::Error::new(err.kind(), format!("can't run {}: {}", program, err)))?;
+
+    let shutdown = Arc::new(AtomicBool::new(false));
+    let forwarder = child
+        .stdin
+        .take()
+        .map(|child_stdin| spawn_stdin_forwarding(child_stdin, mux.stdin(), shutdown.clone()));
+
+    let readers = [
+        pump(
+            child
+                .stdout
+                .take()
+                .map(|out| Box::new(out) as Box<dyn Read + Send>),
+            mux.stdout(),
+        ),
+        pump(
+            child
+                .stderr
+                .take()
+                .map(|err| Box::new(err) as Box<dyn Read + Send>),
+            mux.stderr(),
+        ),
+    ];
+    let status = child.wait()?;
+    shutdown.store(true, Ordering::Relaxed);
+    if let Some(forwarder) = forwarder {
+        forwarder
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("stdin forwarding panicked")))?;
+    }
+    for reader in readers {
+        reader
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("output reader panicked")))?;
+    }
+    Ok(status)
+}
+
+/// Passes our stdin to the command in a separate thread, writing each read as a
+/// stdin frame, until stdin ends, the command stops reading it or `shutdown`
+/// is set. Frames are written whole, so the thread is safe to join at any time.
+fn spawn_stdin_forwarding<W: Write + Send + 'static>(
+    mut child_stdin: ChildStdin,
+    mut frames: MuxStreamWriter<W>,
+    shutdown: Arc<AtomicBool>,
+) -> thread::JoinHandle<io::Result<()>> {
+    thread::spawn(move || {
+        let mut stdin = open_stdin()?;
+        let mut buffer = [0u8; BUFFER_SIZE];
+        while !shutdown.load(Ordering::Relaxed) {
+            if !wait_readable(&stdin)? {
+                continue;
+            }
+            let n_bytes_read = match stdin.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(n_bytes_read) => n_bytes_read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            frames.write_all(&buffer[..n_bytes_read])?;
+            frames.flush()?;
+            if child_stdin.write_all(&buffer[..n_bytes_read]).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Unbuffered handle of our stdin, so polling it tells if a read would block.
+#[cfg(unix)]
+fn open_stdin() -> io::Result<std::fs::File> {
+    use std::os::fd::AsFd;
+    Ok(std::fs::File::from(
+        io::stdin().as_fd().try_clone_to_owned()?,
+    ))
+}
+
+#[cfg(not(unix))]
+fn open_stdin() -> io::Result<io::Stdin> {
+    Ok(io::stdin())
+}
+
+/// Waits up to [`STDIN_POLL_TIMEOUT_MS`] for `stdin` to become readable.
+#[cfg(unix)]
+fn wait_readable(stdin: &std::fs::File) -> io::Result<bool> {
+    use std::os::fd::AsRawFd;
+    let mut poll_fd = libc::pollfd {
+        fd: stdin.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    // SAFETY: `poll_fd` is a single valid pollfd for the duration of the call.
+    match unsafe { libc::poll(&mut poll_fd, 1, STDIN_POLL_TIMEOUT_MS) } {
+        -1 => {
+            let err = io::Error::last_os_error();
+            match err.kind() {
+                io::ErrorKind::Interrupted => Ok(false),
+                _ => Err(err),
+            }
+        }
+        0 => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+/// Without polling, reads block till stdin has data or ends, delaying the
+/// shutdown till then.
+#[cfg(not(unix))]
+fn wait_readable(_stdin: &io::Stdin) -> io::Result<bool> {
+    Ok(true)
+}
+
+/// Frames every read of a child output in a separate thread.
+fn pump<W: Write + Send + 'static>(
+    source: Option<Box<dyn Read + Send>>,
+    mut frames: MuxStreamWriter<W>,
+) -> thread::JoinHandle<io::Result<()>> {
+    thread::spawn(move || {
+        let Some(mut source) = source else {
+            return Ok(());
+        };
+        let mut buffer = [0u8; BUFFER_SIZE];
+        loop {
+            let n_bytes_read = match source.read(&mut buffer) {
+                Ok(0) => return Ok(()),
+                Ok(n_bytes_read) => n_bytes_read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            };
+            frames.write_all(&buffer[..n_bytes_read])?;
+            frames.flush()?;
+        }
+    })
+}
+
+/// Exit code of a finished command, 128 + signal number if it was killed.
+pub fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use docker_stream_codec::{DockerStreamDecoder, StreamType};
+
+    #[test]
+    fn captures_command_output_and_exit_code() {
+        let mux = MuxWriter::new(Vec::new());
+        let command = ["sh", "-c", "echo out; echo err >&2; exit 3"].map(String::from);
+        let status = capture(&command, false, &mux).unwrap();
+        assert_eq!(exit_code(status), 3);
+
+        let output = mux.into_inner().ok().unwrap();
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let mut decoder = DockerStreamDecoder::new();
+        for chunk in decoder.decode(&output) {
+            let chunk = chunk.unwrap();
+            match StreamType::try_from(chunk.stream_type) {
+                Ok(StreamType::Stdout) => stdout.extend_from_slice(chunk.body),
+                Ok(StreamType::Stderr) => stderr.extend_from_slice(chunk.body),
+                _ => panic!("unexpected stream type {}", chunk.stream_type),
+            }
+        }
+        assert_eq!(stdout, b"out\n");
+        assert_eq!(stderr, b"err\n");
+    }
+
+    #[test]
+    fn stops_forwarding_stdin_once_command_exits() {
+        let mux = MuxWriter::new(Vec::new());
+        let command = ["sh", "-c", "exit 0"].map(String::from);
+        capture(&command, true, &mux).unwrap();
+        // All of the stream handles, including the stdin forwarding one, are released
+        assert!(mux.into_inner().is_ok());
+    }
+}
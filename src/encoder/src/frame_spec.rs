@@ -0,0 +1,292 @@
+use std::{
+    error::Error,
+    fmt, fs,
+    io::{self, Write},
+    path::Path,
+};
+
+use docker_stream_codec::{FrameHeader, StreamType, FRAME_HEADER_LENGTH};
+use serde::Deserialize;
+
+/// Frame specification file, listing frames in output order.
+///
+/// ```toml
+/// [[frames]]
+/// stream = "stdout"
+/// text = "hello\n"
+///
+/// [[frames]]
+/// stream = 7
+/// hex = "de ad be ef"
+/// header = "07 00 01 00 00 00 00 04"
+/// ```
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Spec {
+    frames: Vec<FrameSpec>,
+}
+
+/// One frame, with exactly one of the payload fields.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct FrameSpec {
+    stream: StreamSpec,
+    /// Payload as is
+    text: Option<String>,
+    /// Payload with `\n`, `\r`, `\t`, `\0`, `\\` and `\xHH` escapes
+    escaped: Option<String>,
+    /// Payload as hex bytes, optionally separated by whitespace
+    hex: Option<String>,
+    /// Payload read from a file, relative to the spec file
+    file: Option<String>,
+    /// Start of the file slice
+    offset: Option<u64>,
+    /// Length of the file slice, up to the end of the file by default
+    length: Option<u64>,
+    /// Raw 8 header bytes in hex, written instead of the serialized header
+    header: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StreamSpec {
+    Name(String),
+    Type(u8),
+}
+
+/// Writes frames of a spec file (TOML with a `.toml` extension, JSON
+/// otherwise) into `output`.
+pub fn write_frames(spec_path: &Path, output: &mut impl Write) -> Result<(), SpecError> {
+    let contents = fs::read_to_string(spec_path)?;
+    let spec: Spec = match spec_path.extension().and_then(|ext| ext.to_str()) {
+        Some("toml") => {
+            toml::from_str(&contents).map_err(|err| SpecError::Parse(err.to_string()))?
+        }
+        _ => serde_json::from_str(&contents).map_err(|err| SpecError::Parse(err.to_string()))?,
+    };
+    let base_dir = spec_path.parent().unwrap_or(Path::new("."));
+    for (index, frame) in spec.frames.iter().enumerate() {
+        let (header, payload) = frame
+            .build(base_dir)
+            .map_err(|message| SpecError::Frame { index, message })?;
+        output.write_all(&header)?;
+        output.write_all(&payload)?;
+    }
+    Ok(())
+}
+
+impl FrameSpec {
+    fn build(&self, base_dir: &Path) -> Result<([u8; FRAME_HEADER_LENGTH], Vec<u8>), String> {
+        let stream_type = match &self.stream {
+            StreamSpec::Name(name) => name
+                .parse::<StreamType>()
+                .map_err(|_| format!("unknown stream '{}'", name))?
+                .into(),
+            StreamSpec::Type(stream_type) => *stream_type,
+        };
+
+        let payload = match (&self.text, &self.escaped, &self.hex, &self.file) {
+            (Some(text), None, None, None) => text.as_bytes().to_vec(),
+            (None, Some(escaped), None, None) => unescape(escaped)?,
+            (None, None, Some(hex), None) => parse_hex(hex)?,
+            (None, None, None, Some(file)) => self.read_slice(&base_dir.join(file))?,
+            _ => return Err("expected exactly one of text, escaped, hex or file".into()),
+        };
+        if self.file.is_none() && (self.offset.is_some() || self.length.is_some()) {
+            return Err("offset and length can only be used with file".into());
+        }
+        let length = u32::try_from(payload.len())
+            .map_err(|_| format!("payload of {} bytes is too large", payload.len()))?;
+
+        let mut header = [0u8; FRAME_HEADER_LENGTH];
+        match &self.header {
+            Some(hex) => {
+                header = parse_hex(hex)?.try_into().map_err(|bytes: Vec<u8>| {
+                    format!(
+                        "expected {} header bytes, got {}",
+                        FRAME_HEADER_LENGTH,
+                        bytes.len()
+                    )
+                })?
+            }
+            None => FrameHeader::new(stream_type, length).serialize(&mut header),
+        }
+        Ok((header, payload))
+    }
+
+    fn read_slice(&self, path: &Path) -> Result<Vec<u8>, String> {
+        let contents =
+            fs::read(path).map_err(|err| format!("can't read {}: {}", path.display(), err))?;
+        let start = self.offset.unwrap_or(0) as usize;
+        let end = match self.length {
+            Some(length) => start.saturating_add(length as usize),
+            None => contents.len(),
+        };
+        contents.get(start..end).map(<[u8]>::to_vec).ok_or_else(|| {
+            format!(
+                "slice {}..{} is out of {} bytes of {}",
+                start,
+                end,
+                contents.len(),
+                path.display()
+            )
+        })
+    }
+}
+
+fn unescape(value: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        bytes.push(match chars.next() {
+            Some('n') => b'\n',
+            Some('r') => b'\r',
+            Some('t') => b'\t',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                hex_byte(&digits).ok_or_else(|| format!("invalid escape '\\x{}'", digits))?
+            }
+            Some(other) => return Err(format!("unknown escape '\\{}'", other)),
+            None => return Err("trailing backslash".into()),
+        });
+    }
+    Ok(bytes)
+}
+
+fn parse_hex(value: &str) -> Result<Vec<u8>, String> {
+    let digits: Vec<char> = value.chars().filter(|c| !c.is_whitespace()).collect();
+    if digits.len() % 2 == 1 {
+        return Err(format!("odd number of hex digits in '{}'", value));
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair: String = pair.iter().collect();
+            hex_byte(&pair).ok_or_else(|| format!("invalid hex byte '{}'", pair))
+        })
+        .collect()
+}
+
+/// Parses exactly two hex digits, unlike `u8::from_str_radix`, which accepts a
+/// sign and a single digit.
+fn hex_byte(digits: &str) -> Option<u8> {
+    if digits.len() != 2 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    u8::from_str_radix(digits, 16).ok()
+}
+
+#[derive(Debug)]
+pub enum SpecError {
+    Io(io::Error),
+    Parse(String),
+    /// Invalid frame, `index` starting from 0
+    Frame {
+        index: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for SpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "IO error: {}", err),
+            Self::Parse(message) => write!(f, "Invalid frame spec: {}", message),
+            Self::Frame { index, message } => {
+                write!(f, "Invalid frame #{} in spec: {}", index, message)
+            }
+        }
+    }
+}
+
+impl Error for SpecError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for SpecError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn build(json: &str) -> Result<Vec<u8>, String> {
+        let frame: FrameSpec = serde_json::from_str(json).unwrap();
+        let (header, payload) = frame.build(Path::new("."))?;
+        Ok([&header[..], &payload].concat())
+    }
+
+    #[test]
+    fn builds_frames_from_payload_kinds() {
+        assert_eq!(
+            build(r#"{"stream": "stderr", "text": "hi\n"}"#).unwrap(),
+            b"\x02\0\0\0\0\0\0\x03hi\n"
+        );
+        assert_eq!(
+            build(r#"{"stream": 7, "escaped": "\\x00\\xffok\\n"}"#).unwrap(),
+            b"\x07\0\0\0\0\0\0\x05\x00\xffok\n"
+        );
+        assert_eq!(
+            build(r#"{"stream": "stdout", "hex": "de ad", "header": "01 ff 00 00 00 00 00 09"}"#)
+                .unwrap(),
+            b"\x01\xff\0\0\0\0\0\x09\xde\xad"
+        );
+        assert!(build(r#"{"stream": "stdout", "text": "a", "hex": "00"}"#).is_err());
+        assert!(build(r#"{"stream": "stdout", "hex": "00", "header": "01"}"#).is_err());
+    }
+
+    #[test]
+    fn rejects_incomplete_hex_bytes() {
+        assert!(unescape("\\x+1").is_err());
+        assert!(unescape("\\x1g").is_err());
+        assert!(unescape("\\x1").is_err());
+        assert!(parse_hex("+f").is_err());
+        assert!(parse_hex("0x").is_err());
+        assert_eq!(unescape("\\x1F").unwrap(), [0x1f]);
+    }
+
+    #[test]
+    fn reads_toml_spec_with_file_slices() {
+        let dir = std::env::temp_dir().join(format!("frame-spec-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("payload.bin"), b"0123456789").unwrap();
+        let spec_path = dir.join("spec.toml");
+        fs::write(
+            &spec_path,
+            r#"
+[[frames]]
+stream = "stdout"
+file = "payload.bin"
+offset = 2
+length = 3
+
+[[frames]]
+stream = "stdin"
+file = "payload.bin"
+offset = 8
+"#,
+        )
+        .unwrap();
+
+        let mut output = Vec::new();
+        let result = write_frames(&spec_path, &mut output);
+        fs::remove_dir_all(&dir).unwrap();
+        result.unwrap();
+        assert_eq!(output, b"\x01\0\0\0\0\0\0\x03234\x00\0\0\0\0\0\0\x0289");
+    }
+}
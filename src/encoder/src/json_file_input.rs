@@ -0,0 +1,169 @@
+use std::{
+    error::Error,
+    fmt,
+    io::{self, BufRead, Write},
+};
+
+use chrono::{DateTime, Utc};
+use docker_stream_codec::{MuxWriter, StreamType};
+use serde::Deserialize;
+
+/// Entry of docker's json-file log driver, one per line of `<id>-json.log`.
+#[derive(Deserialize)]
+struct JsonLogEntry {
+    log: String,
+    stream: String,
+    /// Only required to prefix lines with timestamps
+    time: Option<String>,
+}
+
+/// Replays json-file log entries as frames, one frame per entry, in file order.
+///
+/// With `timestamps`, each line is prefixed with the entry time, the way
+/// `docker logs --timestamps` does.
+pub fn replay<W: Write>(
+    input: impl BufRead,
+    mux: &MuxWriter<W>,
+    timestamps: bool,
+) -> Result<(), JsonFileError> {
+    // Continuation of a partial entry isn't prefixed with a timestamp
+    let mut partial = [false; 3];
+    for (index, line) in input.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: JsonLogEntry = serde_json::from_str(&line)
+            .map_err(|err| JsonFileError::parse(line_number, err.to_string()))?;
+        let stream_type = entry.stream.parse::<StreamType>().map_err(|_| {
+            JsonFileError::parse(line_number, format!("unknown stream '{}'", entry.stream))
+        })?;
+
+        let mut writer = mux.stream(stream_type);
+        let is_continuation = std::mem::replace(
+            &mut partial[stream_type as usize],
+            !entry.log.ends_with('\n'),
+        );
+        if timestamps && !is_continuation {
+            let time = entry.time.as_deref().ok_or_else(|| {
+                JsonFileError::parse(
+                    line_number,
+                    "missing field `time`, required with timestamps".into(),
+                )
+            })?;
+            let time = DateTime::parse_from_rfc3339(time)
+                .map_err(|err| JsonFileError::parse(line_number, err.to_string()))?;
+            let prefix = time
+                .with_timezone(&Utc)
+                .format("%Y-%m-%dT%H:%M:%S%.9fZ ")
+                .to_string();
+            writer.write_all(&[prefix.as_bytes(), entry.log.as_bytes()].concat())?;
+        } else {
+            writer.write_all(entry.log.as_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum JsonFileError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl JsonFileError {
+    fn parse(line: usize, message: String) -> Self {
+        Self::Parse { line, message }
+    }
+}
+
+impl fmt::Display for JsonFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "IO error: {}", err),
+            Self::Parse { line, message } => {
+                write!(
+                    f,
+                    "Invalid json-file log entry on line {}: {}",
+                    line, message
+                )
+            }
+        }
+    }
+}
+
+impl Error for JsonFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for JsonFileError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const LOG: &str = r#"{"log":"hello\n","stream":"stdout","time":"2023-10-18T12:00:00.12Z"}
+{"log":"partial ","stream":"stderr","time":"2023-10-18T12:00:01Z"}
+{"log":"line\n","stream":"stderr","time":"2023-10-18T12:00:01.5Z"}
+"#;
+
+    #[test]
+    fn replays_entries_as_frames() {
+        let mux = MuxWriter::new(Vec::new());
+        replay(LOG.as_bytes(), &mux, false).unwrap();
+        let output = mux.into_inner().ok().unwrap();
+        assert_eq!(
+            output,
+            [
+                &[1, 0, 0, 0, 0, 0, 0, 6][..],
+                b"hello\n",
+                &[2, 0, 0, 0, 0, 0, 0, 8],
+                b"partial ",
+                &[2, 0, 0, 0, 0, 0, 0, 5],
+                b"line\n",
+            ]
+            .concat()
+        );
+    }
+
+    #[test]
+    fn prefixes_lines_with_timestamps() {
+        let mux = MuxWriter::new(Vec::new());
+        replay(LOG.as_bytes(), &mux, true).unwrap();
+        let output = mux.into_inner().ok().unwrap();
+        let text = String::from_utf8_lossy(&output);
+        assert!(text.contains("2023-10-18T12:00:00.120000000Z hello\n"));
+        assert!(text.contains("2023-10-18T12:00:01.000000000Z partial "));
+        assert!(!text.contains("12:00:01.500000000Z"));
+    }
+
+    #[test]
+    fn requires_time_only_with_timestamps() {
+        let input = "{\"log\":\"x\\n\",\"stream\":\"stdout\"}\n";
+        let mux = MuxWriter::new(Vec::new());
+        replay(input.as_bytes(), &mux, false).unwrap();
+        assert_eq!(mux.into_inner().ok().unwrap(), b"\x01\0\0\0\0\0\0\x02x\n");
+
+        let mux = MuxWriter::new(Vec::new());
+        let err = replay(input.as_bytes(), &mux, true).unwrap_err();
+        assert!(matches!(err, JsonFileError::Parse { line: 1, .. }));
+    }
+
+    #[test]
+    fn reports_line_of_invalid_entry() {
+        let mux = MuxWriter::new(Vec::new());
+        let input = "{\"log\":\"x\",\"stream\":\"stdout\",\"time\":\"2023-10-18T12:00:00Z\"}\n{}\n";
+        let err = replay(input.as_bytes(), &mux, false).unwrap_err();
+        assert!(matches!(err, JsonFileError::Parse { line: 2, .. }));
+    }
+}
@@ -3,40 +3,192 @@ use std::{
     fs::File,
     io::{self, BufReader, BufWriter},
     io::{Error as IoError, Write},
+    path::Path,
 };
 
-use crate::{
-    args::Args,
-    docker_stream_multiplexer::{DockerStreamMultiplexer, StreamSourceInfo},
+use crate::args::{Args, FaultSpec, Framing, Schedule};
+use docker_stream_codec::{
+    DockerStreamMultiplexer, FaultInjection, FramingMode, MuxWriter, ProportionalScheduler,
+    RandomScheduler, RoundRobinScheduler, SequentialScheduler, SourceScheduler, StreamSourceInfo,
+    WeightedScheduler,
 };
 
 mod args;
-mod docker_stream_multiplexer;
-mod frame_header;
+mod command_capture;
+mod frame_spec;
+mod json_file_input;
 
 fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse()?;
 
+    let mut output: BufWriter<Box<dyn Write + Send>> = match args.output.as_str() {
+        "-" => BufWriter::new(Box::new(std::io::stdout())),
+        _ => BufWriter::new(Box::new(File::create(&args.output)?)),
+    };
+
+    if !args.command.is_empty() {
+        let mux = MuxWriter::new(output);
+        let status = command_capture::capture(&args.command, args.forward_stdin, &mux)?;
+        if let Ok(mut output) = mux.into_inner() {
+            output.flush()?;
+        }
+        let exit_code = command_capture::exit_code(status);
+        if exit_code != 0 {
+            std::process::exit(exit_code);
+        }
+        return Ok(());
+    }
+
+    if let Some(spec) = &args.spec {
+        frame_spec::write_frames(Path::new(spec), &mut output)
+            .map_err(|err| format!("{}: {}", spec, err))?;
+        output.flush()?;
+        return Ok(());
+    }
+
+    if !args.json_file.is_empty() {
+        let mux = MuxWriter::new(output);
+        for filename in &args.json_file {
+            let input: Box<dyn io::BufRead> = match filename.as_str() {
+                "-" => Box::new(io::stdin().lock()),
+                _ => Box::new(BufReader::new(File::open(filename)?)),
+            };
+            json_file_input::replay(input, &mux, args.timestamps)
+                .map_err(|err| format!("{}: {}", filename, err))?;
+        }
+        if let Ok(mut output) = mux.into_inner() {
+            output.flush()?;
+        }
+        return Ok(());
+    }
+
     let sources: Result<Vec<StreamSourceInfo>, IoError> = args
         .get_sources()
         .map(|source_file| {
-            let file = File::open(source_file.filename)?;
-            Ok(StreamSourceInfo {
-                stream_type: source_file.stream_type,
-                source: Box::new(BufReader::new(file)),
-            })
+            stream_source(File::open(source_file.filename)?, source_file.stream_type)
         })
         .collect();
     let sources = sources?;
+    if sources.iter().any(|source| source.size.is_none()) {
+        args.check_buffered_frame_max()?;
+    }
+    let scheduler = create_scheduler(&args, &sources);
 
-    let mut output: BufWriter<Box<dyn Write>> = match args.output.as_str() {
-        "-" => BufWriter::new(Box::new(std::io::stdout())),
-        _ => BufWriter::new(Box::new(File::create(args.output)?)),
-    };
-
+    let seed = args.seed.unwrap_or_else(|| {
+        let seed = rand::random();
+        eprintln!(
+            "Seed: {} (use --seed {} to regenerate the stream)",
+            seed, seed
+        );
+        seed
+    });
     let mut multiplexer =
-        DockerStreamMultiplexer::new(sources, args.frame_max, args.frame_min as u32);
+        DockerStreamMultiplexer::new(sources, args.frame_max, args.frame_min as u32)
+            .with_seed(seed)
+            .with_scheduler(scheduler)
+            .with_framing(match args.framing {
+                Framing::Bytes => FramingMode::Bytes,
+                Framing::Lines => FramingMode::Lines(args.lines),
+                Framing::LineBreak => FramingMode::LineBreak,
+            })
+            .with_faults(args.faults.iter().fold(
+                FaultInjection::new(),
+                |faults, fault| match *fault {
+                    FaultSpec::Rate(kind, rate) => faults.with_rate(kind, rate),
+                    FaultSpec::Frame(kind, index) => faults.at_frame(kind, index),
+                },
+            ));
 
     io::copy(&mut multiplexer, &mut output)?;
+    output.flush()?;
+
+    if let Some(fault_log) = &args.fault_log {
+        let mut log = BufWriter::new(File::create(fault_log)?);
+        for fault in multiplexer.injected_faults() {
+            let entry = serde_json::json!({
+                "kind": fault.kind.to_string(),
+                "frame": fault.frame_index,
+                "offset": fault.offset,
+                "detail": fault.detail,
+            });
+            serde_json::to_writer(&mut log, &entry)?;
+            log.write_all(b"\n")?;
+        }
+        log.flush()?;
+    }
     Ok(())
 }
+
+/// Source reading `file`. Only the length of regular files is known, pipes and
+/// devices report zero.
+fn stream_source(file: File, stream_type: u8) -> Result<StreamSourceInfo, IoError> {
+    let metadata = file.metadata()?;
+    Ok(StreamSourceInfo {
+        stream_type,
+        source: Box::new(BufReader::new(file)),
+        size: metadata.is_file().then_some(metadata.len()),
+    })
+}
+
+fn create_scheduler(args: &Args, sources: &[StreamSourceInfo]) -> Box<dyn SourceScheduler> {
+    match args.schedule {
+        Schedule::Random => Box::new(RandomScheduler),
+        Schedule::RoundRobin => Box::new(RoundRobinScheduler::default()),
+        Schedule::Sequential => Box::new(SequentialScheduler),
+        Schedule::Weighted => {
+            let weights = sources
+                .iter()
+                .map(|source| {
+                    args.weights
+                        .iter()
+                        .rev()
+                        .find(|(stream_type, _)| *stream_type as u8 == source.stream_type)
+                        .map_or(1, |(_, weight)| *weight)
+                })
+                .collect();
+            Box::new(WeightedScheduler::new(weights))
+        }
+        Schedule::Proportional => Box::new(ProportionalScheduler::new(
+            sources
+                .iter()
+                .map(|source| source.size.unwrap_or(0))
+                .collect(),
+        )),
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+
+    /// Read and write ends of a new pipe.
+    fn pipe() -> io::Result<(File, File)> {
+        let mut fds = [0; 2];
+        // SAFETY: `fds` has room for both descriptors, which are owned by the
+        // returned files only.
+        unsafe {
+            if libc::pipe(fds.as_mut_ptr()) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok((File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])))
+        }
+    }
+
+    #[test]
+    fn multiplexes_pipe_sources() {
+        let (reader, mut writer) = pipe().unwrap();
+        writer.write_all(b"hello world\n").unwrap();
+        drop(writer);
+
+        let source = stream_source(reader, 1).unwrap();
+        assert_eq!(source.size, None);
+        let mut output = Vec::new();
+        DockerStreamMultiplexer::new(vec![source], 200, 100)
+            .with_seed(1)
+            .read_to_end(&mut output)
+            .unwrap();
+        assert_eq!(output, b"\x01\0\0\0\0\0\0\x0chello world\n");
+    }
+}